@@ -1,6 +1,9 @@
 use std::collections::VecDeque;
 use std::fmt::{self, Display};
 
+#[cfg(feature = "trace")]
+use intcode::trace::{Trace, Tracer};
+
 // Opcodes
 // 01 ADD op1 op2 addr
 // 02 MULTIPLY op1 op2 addr
@@ -17,6 +20,8 @@ pub struct Computer {
     pc: usize,
     output: Vec<i32>,
     input: VecDeque<i32>,
+    #[cfg(feature = "trace")]
+    trace: Tracer,
 }
 
 impl Computer {
@@ -26,6 +31,8 @@ impl Computer {
             pc: 0,
             output: vec![],
             input: VecDeque::new(),
+            #[cfg(feature = "trace")]
+            trace: Tracer::new(),
         }
     }
 
@@ -37,6 +44,19 @@ impl Computer {
         &self.output
     }
 
+    /// Start recording every instruction that is executed, in the format of
+    /// `intcode::trace`, so a run can be diffed against the intcode crate with
+    /// `intcode::trace::replay`. Needs the `trace` feature.
+    #[cfg(feature = "trace")]
+    pub fn enable_trace(&mut self) {
+        self.trace.enable();
+    }
+
+    #[cfg(feature = "trace")]
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
     pub fn debug(&self) {
         let mut debugger = self.clone();
         while let Ok(instr) = debugger.next_instr() {
@@ -56,6 +76,8 @@ impl Computer {
             .memory
             .get_mut(pos)
             .ok_or("Tried to write past memory")? = value;
+        #[cfg(feature = "trace")]
+        self.trace.write(pos, value.into());
         Ok(())
     }
 
@@ -68,7 +90,11 @@ impl Computer {
 
     pub fn run(&mut self) -> Result<()> {
         loop {
+            #[cfg(feature = "trace")]
+            let pc = self.pc;
             let instr = self.next_instr()?;
+            #[cfg(feature = "trace")]
+            self.trace.instruction(&self.memory, pc, 0)?;
             // println!("{}", instr);
             use Instruction::*;
             match instr {
@@ -89,8 +115,15 @@ impl Computer {
                 Input { result_location } => {
                     let value = self.input.pop_front().ok_or("Nothing in input queue")?;
                     self.set(result_location, value)?;
+                    #[cfg(feature = "trace")]
+                    self.trace.input(value.into());
+                }
+                Output { param } => {
+                    let value = self.get_param(param)?;
+                    self.output.push(value);
+                    #[cfg(feature = "trace")]
+                    self.trace.output(value.into());
                 }
-                Output { param } => self.output.push(self.get_param(param)?),
                 JumpIfTrue { check, jump_to } => {
                     if self.get_param(check)? > 0 {
                         self.pc = self.get_param(jump_to)? as usize;
@@ -551,6 +584,29 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "trace")]
+    #[test]
+    fn trace_matches_intcode() -> Result<()> {
+        let input = include_str!("../input/input.txt");
+        let program: Vec<i32> = input
+            .trim()
+            .split(',')
+            .map(|val| val.parse())
+            .collect::<std::result::Result<_, _>>()?;
+        let mut computer = Computer::from_mem(program.clone());
+        computer.enable_trace();
+        computer.input(5);
+        computer.run()?;
+        let trace = computer.take_trace().unwrap();
+        assert_eq!(trace.events.last().unwrap().instr, "HALT");
+
+        let program: Vec<i64> = program.into_iter().map(i64::from).collect();
+        if let Some(divergence) = intcode::trace::replay(&program, &trace) {
+            panic!("{}", divergence);
+        }
+        Ok(())
+    }
+
     #[test]
     fn day5_part2_test_programs() -> Result<()> {
         let is_eight = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
@@ -13,6 +13,16 @@ fn main() -> Result<()> {
     println!("Part1: {}", part1(&input)?);
     println!("Part2: {}", part2(&input)?);
 
+    // Given a path, save a trace of part 2 there, to diff against `intcode-trace`
+    #[cfg(feature = "trace")]
+    if let Some(path) = std::env::args().nth(1) {
+        let mut computer = Computer::from_mem(parse_program(&input)?);
+        computer.enable_trace();
+        computer.input(5);
+        computer.run()?;
+        computer.take_trace().unwrap_or_default().save(path)?;
+    }
+
     Ok(())
 }
 
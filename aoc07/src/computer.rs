@@ -5,6 +5,7 @@ use async_std::{
     sync::{channel, Receiver, Sender},
     task,
 };
+use intcode::trace::{Trace, Tracer};
 
 // Opcodes
 // 01 ADD op1 op2 addr
@@ -20,6 +21,7 @@ pub struct Computer {
     pc: usize,
     output: Option<Sender<i32>>,
     input: Option<Receiver<i32>>,
+    trace: Tracer,
 }
 
 impl Computer {
@@ -29,6 +31,7 @@ impl Computer {
             pc: 0,
             output: None,
             input: None,
+            trace: Tracer::new(),
         }
     }
 
@@ -52,6 +55,17 @@ impl Computer {
         receiver
     }
 
+    /// Start recording every instruction that is executed, in the format of
+    /// `intcode::trace`, so a run can be diffed against the intcode crate with
+    /// `intcode::trace::replay`.
+    pub fn enable_trace(&mut self) {
+        self.trace.enable();
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
     pub fn debug(&self) {
         let mut debugger = self.clone();
         while let Ok(instr) = debugger.next_instr() {
@@ -71,6 +85,7 @@ impl Computer {
             .memory
             .get_mut(pos)
             .ok_or(anyhow!("Tried to write past memory"))? = value;
+        self.trace.write(pos, value.into());
         Ok(())
     }
 
@@ -93,7 +108,9 @@ impl Computer {
 
     pub async fn run(&mut self) -> Result<()> {
         loop {
+            let pc = self.pc;
             let instr = self.next_instr()?;
+            self.trace.instruction(&self.memory, pc, 0)?;
             // println!("{}", instr);
             use Instruction::*;
             match instr {
@@ -119,12 +136,17 @@ impl Computer {
                             .await
                             .ok_or(anyhow!("Input sender-end dropped"))?;
                         self.set(result_location, value)?;
+                        self.trace.input(value.into());
                     }
                 },
-                Output { param } => match &self.output {
-                    None => Err(anyhow!("Tried to write to unconnected output"))?,
-                    Some(output) => output.send(self.get_param(param)?).await,
-                },
+                Output { param } => {
+                    let value = self.get_param(param)?;
+                    match &self.output {
+                        None => Err(anyhow!("Tried to write to unconnected output"))?,
+                        Some(output) => output.send(value).await,
+                    }
+                    self.trace.output(value.into());
+                }
                 JumpIfTrue { check, jump_to } => {
                     if self.get_param(check)? > 0 {
                         self.pc = self.get_param(jump_to)? as usize;
@@ -361,6 +383,32 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn trace_matches_intcode() -> Result<()> {
+        let program: Vec<i32> = include_str!("../input/input.txt")
+            .trim()
+            .split(',')
+            .map(|val| val.parse())
+            .collect::<std::result::Result<_, _>>()?;
+        let mut computer = Computer::from_mem(program.clone());
+        computer.enable_trace();
+        let (input_sender, input_receiver) = channel(2);
+        let output = computer.create_output_channel();
+        computer.connect_input(input_receiver);
+        input_sender.send(4).await;
+        input_sender.send(0).await;
+        computer.run().await?;
+        assert!(output.recv().await.is_some());
+        let trace = computer.take_trace().unwrap();
+        assert_eq!(trace.events.last().unwrap().instr, "HALT");
+
+        let program: Vec<i64> = program.into_iter().map(i64::from).collect();
+        if let Some(divergence) = intcode::trace::replay(&program, &trace) {
+            panic!("{}", divergence);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_next_i32() -> Result<()> {
         let mut program = Computer::from_mem(vec![1, 0, 2]);
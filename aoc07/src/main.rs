@@ -1,10 +1,13 @@
 mod computer;
 
+use std::convert::TryFrom;
+use std::env;
 use std::io::{self, Read};
 
 use anyhow::{anyhow, Result};
 use async_std::{sync::channel, task};
 use futures::future::join_all;
+use intcode::pipeline::Pipeline;
 use itertools::Itertools;
 
 use computer::Computer;
@@ -16,6 +19,11 @@ fn main() -> Result<()> {
     println!("Part1: {}", part1(&input)?);
     println!("Part2: {}", part2(&input)?);
 
+    // Given a path, save a trace of a single amplifier there, to diff against `intcode-trace`
+    if let Some(path) = env::args().nth(1) {
+        task::block_on(trace_amplifier(parse_program(&input)?, path))?;
+    }
+
     Ok(())
 }
 
@@ -27,6 +35,19 @@ fn parse_program(program: &str) -> Result<Vec<i32>> {
         .collect::<Result<Vec<i32>>>()?)
 }
 
+async fn trace_amplifier(program: Vec<i32>, path: String) -> Result<()> {
+    let mut computer = Computer::from_mem(program);
+    computer.enable_trace();
+    let (sender, receiver) = channel(2);
+    computer.connect_input(receiver);
+    let output = computer.create_output_channel();
+    sender.send(0).await;
+    sender.send(0).await;
+    computer.run().await?;
+    output.recv().await;
+    computer.take_trace().unwrap_or_default().save(path)
+}
+
 fn part1(input: &str) -> Result<i32> {
     Ok(task::block_on(max_thruster_signal(parse_program(input)?))?)
 }
@@ -56,41 +77,27 @@ async fn get_thruster_signal(program: Vec<i32>, settings: &[i32]) -> Result<i32>
         .ok_or(anyhow!("Could not get output"))?)
 }
 
+// Runs the amplifiers as an `intcode::pipeline::Pipeline`, so a program that stops
+// sending fails with a deadlock instead of hanging
 async fn get_thruster_signal_feedback(program: Vec<i32>, settings: &[i32]) -> Result<i32> {
-    let (start_sender, start_receiver) = channel(10);
-    let mut last_sender = start_sender.clone();
-    let mut last_receiver = start_receiver.clone();
-
-    let mut computers = Vec::new();
-
-    for phase in settings {
-        last_sender.send(*phase).await;
-        let mut computer = Computer::from_mem(program.clone());
-
-        let (sender, receiver) = channel(10);
-
-        computer.connect_input(last_receiver.clone());
-        computer.connect_output(sender.clone());
-        last_sender = sender;
-        last_receiver = receiver;
-        computers.push(computer);
+    let program: Vec<i64> = program.into_iter().map(i64::from).collect();
+    let mut pipeline = Pipeline::new();
+    for &phase in settings {
+        let amplifier = pipeline.add(intcode::Computer::from_mem(program.clone()));
+        pipeline.push_input(amplifier, phase.into());
+        if amplifier > 0 {
+            pipeline.connect(amplifier - 1, amplifier);
+        }
     }
-
-    computers
-        .last_mut()
-        .unwrap()
-        .connect_output(start_sender.clone());
-
-    let tasks = join_all(computers.into_iter().map(|computer| computer.spawn())).await;
-
-    start_sender.send(0).await;
-
-    join_all(tasks).await;
-
-    Ok(start_receiver
-        .recv()
-        .await
-        .ok_or(anyhow!("Could not get output"))?)
+    pipeline.connect(settings.len() - 1, 0);
+    pipeline.push_input(0, 0);
+
+    let output = pipeline.run().await?;
+    let signal = output
+        .last()
+        .and_then(|output| output.last())
+        .ok_or(anyhow!("Could not get output"))?;
+    Ok(i32::try_from(*signal)?)
 }
 
 async fn max_thruster_signal(program: Vec<i32>) -> Result<i32> {
@@ -132,6 +139,7 @@ fn part2(input: &str) -> Result<i32> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use intcode::scheduler::SchedulerError;
 
     #[async_std::test]
     async fn test_get_thruster_signal() -> Result<()> {
@@ -196,6 +204,21 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn feedback_deadlock() {
+        // Wants a third input before it outputs anything
+        let program = vec![3, 20, 3, 20, 3, 20, 4, 20, 99];
+        let err = get_thruster_signal_feedback(program, &[1, 2])
+            .await
+            .unwrap_err();
+        match err.downcast_ref::<SchedulerError>() {
+            Some(SchedulerError::Deadlock(deadlock)) => {
+                assert_eq!(deadlock.blocked, vec![(0, 4), (1, 2)])
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+    }
+
     #[async_std::test]
     async fn test_max_thruster_signal() -> Result<()> {
         assert_eq!(
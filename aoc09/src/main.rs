@@ -30,7 +30,7 @@ async fn part1(input: &str) -> Result<i64> {
     let input = computer.create_input_channel();
     let output = computer.create_output_channel();
     input.send(1).await;
-    let task = computer.spawn().await;
+    let task = computer.spawn();
     let mut last_output = 0;
     while let Some(output) = output.recv().await {
         println!("{}", output);
@@ -45,7 +45,7 @@ async fn part2(input: &str) -> Result<i64> {
     let input = computer.create_input_channel();
     let output = computer.create_output_channel();
     input.send(2).await;
-    let task = computer.spawn().await;
+    let task = computer.spawn();
     let mut last_output = 0;
     while let Some(output) = output.recv().await {
         println!("{}", output);
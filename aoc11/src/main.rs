@@ -98,7 +98,7 @@ impl Locomotion {
 
 #[async_trait]
 impl intcode::IO for Locomotion {
-    async fn input(&self) -> Result<i64> {
+    async fn input(&mut self) -> Result<i64> {
         Ok(match self.get(self.x, self.y) {
             Color::White => 1,
             Color::Black => 0,
@@ -1,3 +1,4 @@
+#[derive(Debug, Clone)]
 pub struct Ai {}
 
 impl Ai {
@@ -15,3 +16,75 @@ impl Ai {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use intcode::record::{Recorder, Recording, Replay};
+    use intcode::{Computer, IO};
+
+    // Plays the game with the AI, tracking the ball and paddle like the UI does
+    #[derive(Debug, Clone)]
+    struct Player {
+        ai: Ai,
+        tile: Vec<i64>,
+        ball: (i16, i16),
+        paddle: (i16, i16),
+        score: i64,
+    }
+
+    #[async_trait]
+    impl IO for Player {
+        async fn input(&mut self) -> Result<i64> {
+            Ok(self.ai.get_next(self.paddle, self.ball) as i64)
+        }
+
+        async fn output(&mut self, data: i64) -> Result<()> {
+            self.tile.push(data);
+            if let [x, y, tile_id] = self.tile[..] {
+                match tile_id {
+                    score if x == -1 && y == 0 => self.score = score,
+                    3 => self.paddle = (x as i16, y as i16),
+                    4 => self.ball = (x as i16, y as i16),
+                    _ => {}
+                }
+                self.tile.clear();
+            }
+            Ok(())
+        }
+    }
+
+    #[async_std::test]
+    async fn replay_game() -> Result<()> {
+        let mut program = intcode::parse_program(include_str!("../input/input.txt"))?;
+        // Insert quarters
+        program[0] = 2;
+        let player = Player {
+            ai: Ai::new(),
+            tile: Vec::new(),
+            ball: (0, 0),
+            paddle: (0, 0),
+            score: 0,
+        };
+        let mut computer = Computer::from_mem(program.clone()).with_io(Recorder::new(player));
+        computer.run().await?;
+        let recorder = computer.into_io();
+        assert!(recorder.inner.score > 0);
+
+        let path = std::env::temp_dir().join(format!("aoc13-game-{}.jsonl", std::process::id()));
+        recorder.recording.save(&path)?;
+        let recording = Recording::load(&path);
+        fs::remove_file(&path)?;
+        let recording = recording?;
+        assert_eq!(recording, recorder.recording);
+
+        let mut computer = Computer::from_mem(program).with_io(Replay::new(&recording));
+        computer.run().await?;
+        assert_eq!(computer.into_io().remaining(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+//! Runs the game with the computer in our hands instead of spawned, so it can be saved in
+//! the middle and continued later with `aoc13 --resume <file>`.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use async_std::sync::Receiver;
+use serde::{Deserialize, Serialize};
+
+use intcode::snapshot::Snapshot;
+use intcode::{Computer, StopReason};
+
+/// Where the UI saves the game.
+pub const SAVE_FILE: &str = "aoc13-save.json";
+
+/// A game saved in the middle. The program only redraws what changes, so the screen it has
+/// drawn so far is saved with the computer, as `[x, y, tile_id]`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub snapshot: Snapshot,
+    pub screen: Vec<[i64; 3]>,
+}
+
+impl SavedGame {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SavedGame> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Joystick(i64),
+    Save(PathBuf),
+}
+
+/// Play a new game, or continue `saved`, following `commands`. Every tile is passed to
+/// `draw`, starting with the saved screen. Returns when the game is over or `commands`
+/// is closed.
+pub async fn play<F>(
+    saved: Option<SavedGame>,
+    commands: Receiver<Command>,
+    mut draw: F,
+) -> Result<()>
+where
+    F: FnMut(i64, i64, i64),
+{
+    let (mut computer, mut screen) = match saved {
+        Some(saved) => {
+            let screen: BTreeMap<_, _> = saved
+                .screen
+                .into_iter()
+                .map(|[x, y, tile_id]| ((x, y), tile_id))
+                .collect();
+            (Computer::from_snapshot(saved.snapshot), screen)
+        }
+        None => {
+            let mut program = intcode::parse_program(include_str!("../input/input.txt"))?;
+            // Insert quarters
+            program[0] = 2;
+            (Computer::from_mem(program), BTreeMap::new())
+        }
+    };
+    for (&(x, y), &tile_id) in &screen {
+        draw(x, y, tile_id);
+    }
+
+    let mut tile = Vec::new();
+    loop {
+        match computer.resume()? {
+            StopReason::ProducedOutput(value) => {
+                tile.push(value);
+                if let [x, y, tile_id] = tile[..] {
+                    screen.insert((x, y), tile_id);
+                    draw(x, y, tile_id);
+                    tile.clear();
+                }
+            }
+            // The program reads the joystick between tiles, so the screen is complete here
+            StopReason::NeedsInput => match commands.recv().await {
+                Some(Command::Joystick(value)) => computer.push_input(value),
+                Some(Command::Save(path)) => {
+                    let saved = SavedGame {
+                        snapshot: computer.snapshot(),
+                        screen: screen
+                            .iter()
+                            .map(|(&(x, y), &tile_id)| [x, y, tile_id])
+                            .collect(),
+                    };
+                    saved.save(&path)?;
+                    println!("Saved the game to {}", path.display());
+                }
+                None => return Ok(()),
+            },
+            StopReason::Halted => return Ok(()),
+            StopReason::Breakpoint | StopReason::Watchpoint { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use async_std::sync::channel;
+
+    // Play with `commands`, and return the final screen
+    async fn screen(saved: Option<SavedGame>, commands: Vec<Command>) -> Result<Vec<[i64; 3]>> {
+        let (sender, receiver) = channel(commands.len().max(1));
+        for command in commands {
+            sender.send(command).await;
+        }
+        drop(sender);
+        let mut screen = BTreeMap::new();
+        play(saved, receiver, |x, y, tile_id| {
+            screen.insert((x, y), tile_id);
+        })
+        .await?;
+        Ok(screen
+            .into_iter()
+            .map(|((x, y), tile_id)| [x, y, tile_id])
+            .collect())
+    }
+
+    #[async_std::test]
+    async fn save_and_resume() -> Result<()> {
+        // Never move. The ball is lost after 10 moves
+        let still = |moves| vec![Command::Joystick(0); moves];
+        let path = std::env::temp_dir().join(format!("aoc13-save-{}.json", std::process::id()));
+
+        let mut commands = still(5);
+        commands.push(Command::Save(path.clone()));
+        let saved_screen = screen(None, commands).await?;
+        let saved = SavedGame::load(&path);
+        fs::remove_file(&path)?;
+        let saved = saved?;
+        assert_eq!(saved.screen, saved_screen);
+
+        let resumed = screen(Some(saved), still(100)).await?;
+        assert_eq!(resumed, screen(None, still(100)).await?);
+        Ok(())
+    }
+}
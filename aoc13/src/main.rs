@@ -1,22 +1,35 @@
 mod ai;
+mod game;
 mod ui;
 
 use std::collections::HashSet;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use async_std::sync::Receiver;
 
 use intcode::Computer;
 
+use game::SavedGame;
+
 #[async_std::main]
 async fn main() -> Result<()> {
     let input = include_str!("../input/input.txt");
     // let mut input = String::new();
     // io::stdin().read_to_string(&mut input)?;
 
+    // `--resume <file>` continues a game saved with S
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let saved = match &args[..] {
+        [] => None,
+        [flag, path] if flag == "--resume" => {
+            Some(SavedGame::load(path).with_context(|| format!("Could not load game {}", path))?)
+        }
+        _ => bail!("Usage: aoc13 [--resume <file>]"),
+    };
+
     println!("Part1: {}", part1(&input).await?);
 
-    part2()?;
+    part2(saved)?;
 
     Ok(())
 }
@@ -62,8 +75,8 @@ async fn part1(input: &str) -> Result<usize> {
     Ok(tiles.len())
 }
 
-fn part2() -> Result<()> {
-    ui::run()?;
+fn part2(saved: Option<SavedGame>) -> Result<()> {
+    ui::run(saved)?;
 
     // let mut computer = Computer::from_mem(intcode::parse_program(input)?);
 
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
+use async_std::sync::channel;
 use pixels::{wgpu::Surface, Pixels, SurfaceTexture};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, VirtualKeyCode, WindowEvent};
@@ -12,7 +13,7 @@ use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
-use intcode::Computer;
+use crate::game::{self, Command, SavedGame, SAVE_FILE};
 
 const WIDTH: u32 = 44;
 const HEIGHT: u32 = 25;
@@ -42,20 +43,19 @@ struct Tile {
     tile_type: TileType,
 }
 
-async fn read_tile(stream: &async_std::sync::Receiver<i64>) -> Option<Tile> {
-    let x = stream.recv().await?;
-    let y = stream.recv().await?;
-    let tile_id = stream.recv().await?;
-    let tile_type = match tile_id {
-        0 => TileType::Empty,
-        1 => TileType::Wall,
-        2 => TileType::Block,
-        3 => TileType::HorizontalPaddle,
-        4 => TileType::Ball,
-        score if x == -1 && y == 0 => TileType::Score(score),
-        _ => panic!("Invalid type type {}", tile_id),
-    };
-    Some(Tile { x, y, tile_type })
+impl Tile {
+    fn new(x: i64, y: i64, tile_id: i64) -> Tile {
+        let tile_type = match tile_id {
+            0 => TileType::Empty,
+            1 => TileType::Wall,
+            2 => TileType::Block,
+            3 => TileType::HorizontalPaddle,
+            4 => TileType::Ball,
+            score if x == -1 && y == 0 => TileType::Score(score),
+            _ => panic!("Invalid type type {}", tile_id),
+        };
+        Tile { x, y, tile_type }
+    }
 }
 
 // #[derive(Debug, Clone)]
@@ -66,7 +66,7 @@ async fn read_tile(stream: &async_std::sync::Receiver<i64>) -> Option<Tile> {
 
 // #[async_trait]
 // impl intcode::IO for GameIO {
-//     async fn input(&self) -> Result<i64> {
+//     async fn input(&mut self) -> Result<i64> {
 //         async_std::task::sleep(Duration::from_secs(1)).await;
 //         let value = self.input.load(Ordering::Relaxed).into();
 //         println!("Input {}", value);
@@ -79,24 +79,21 @@ async fn read_tile(stream: &async_std::sync::Receiver<i64>) -> Option<Tile> {
 //     }
 // }
 
-fn spawn_computer(sender: EventLoopProxy<Tile>) -> Result<impl Fn(i64) -> ()> {
+fn spawn_computer(sender: EventLoopProxy<Tile>, saved: Option<SavedGame>) -> impl Fn(Command) {
     // let (tx, output) = channel(100);
     // let input = Arc::new(AtomicI8::new(0));
     // let io = GameIO { output: tx, input };
 
-    let mut program = intcode::parse_program(include_str!("../input/input.txt"))?;
-    program[0] = 2;
-    println!("{}", program[0]);
-    let mut computer = Computer::from_mem(program);
-    let output = computer.create_output_channel();
-    let input = computer.create_input_channel();
-
-    computer.spawn();
+    let (commands, receiver) = channel(100);
     async_std::task::spawn(async move {
-        while let Some(tile) = read_tile(&output).await {
-            if let Err(err) = sender.send_event(tile) {
+        let result = game::play(saved, receiver, |x, y, tile_id| {
+            if let Err(err) = sender.send_event(Tile::new(x, y, tile_id)) {
                 eprintln!("Error sending tile: {}", err);
             }
+        })
+        .await;
+        if let Err(err) = result {
+            eprintln!("Error in the game: {}", err);
         }
         println!("HALTED");
     });
@@ -108,17 +105,18 @@ fn spawn_computer(sender: EventLoopProxy<Tile>) -> Result<impl Fn(i64) -> ()> {
     //     }
     // });
 
-    Ok(move |value| {
-        let input = input.clone();
-        async_std::task::spawn(async move { input.send(value).await });
-    })
+    move |command| {
+        let commands = commands.clone();
+        async_std::task::spawn(async move { commands.send(command).await });
+    }
 }
 
-pub fn run() -> Result<()> {
+/// Play the game, or continue `saved`. Press S to save it to `SAVE_FILE`.
+pub fn run(saved: Option<SavedGame>) -> Result<()> {
     env_logger::init();
     let event_loop = EventLoop::with_user_event();
 
-    let send_input = spawn_computer(event_loop.create_proxy())?;
+    let send_command = spawn_computer(event_loop.create_proxy(), saved);
 
     let mut input = WinitInputHelper::new();
     let window = {
@@ -166,6 +164,10 @@ pub fn run() -> Result<()> {
                         return;
                     }
 
+                    if input.key_pressed(VirtualKeyCode::S) {
+                        send_command(Command::Save(SAVE_FILE.into()));
+                    }
+
                     // let joystick = if input.key_held(VirtualKeyCode::Left) {
                     //     -1
                     // } else if input.key_held(VirtualKeyCode::Right) {
@@ -194,7 +196,8 @@ pub fn run() -> Result<()> {
 
                     let step = Duration::from_millis(50);
                     while dt > step {
-                        send_input(ai.get_next(world.paddle, world.ball) as i64);
+                        let joystick = ai.get_next(world.paddle, world.ball) as i64;
+                        send_command(Command::Joystick(joystick));
                         dt -= step;
                     }
 
@@ -0,0 +1,119 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use intcode::threaded::Backend;
+use intcode::{Computer, StopReason};
+
+const BOOST: &str = include_str!("../../aoc09/input/input.txt");
+const BREAKOUT: &str = include_str!("../../aoc13/input/input.txt");
+
+type Setup = fn(&mut Computer);
+
+/// The ways of running a program we compare.
+fn engines() -> Vec<(&'static str, Setup)> {
+    vec![
+        ("cached", |_| {}),
+        ("uncached", |computer| computer.set_decode_cache(false)),
+        ("threaded", |computer| {
+            computer.set_backend(Backend::Threaded)
+        }),
+    ]
+}
+
+fn boost(program: &[i64], input: i64, setup: Setup) -> i64 {
+    let mut computer = Computer::from_mem(program.to_vec());
+    setup(&mut computer);
+    computer.push_input(input);
+    match computer.resume().unwrap() {
+        StopReason::ProducedOutput(value) => value,
+        reason => panic!("Unexpected stop: {:?}", reason),
+    }
+}
+
+/// Play the whole game, following the ball with the paddle. Returns the final score.
+fn breakout(program: &[i64], setup: Setup) -> i64 {
+    let mut program = program.to_vec();
+    // Insert coins
+    program[0] = 2;
+    let mut computer = Computer::from_mem(program);
+    setup(&mut computer);
+    let (mut ball, mut paddle, mut score) = (0, 0, 0);
+    let mut tile = Vec::with_capacity(3);
+    loop {
+        match computer.resume().unwrap() {
+            StopReason::ProducedOutput(value) => {
+                tile.push(value);
+                if tile.len() == 3 {
+                    match tile[..] {
+                        [-1, 0, value] => score = value,
+                        [x, _, 3] => paddle = x,
+                        [x, _, 4] => ball = x,
+                        _ => {}
+                    }
+                    tile.clear();
+                }
+            }
+            StopReason::NeedsInput => computer.push_input((ball - paddle).signum()),
+            StopReason::Halted => return score,
+            reason => panic!("Unexpected stop: {:?}", reason),
+        }
+    }
+}
+
+fn bench_boost(c: &mut Criterion) {
+    let program = intcode::parse_program(BOOST).unwrap();
+    let mut group = c.benchmark_group("day9 boost");
+    group.sample_size(20);
+    for (name, setup) in engines() {
+        assert_eq!(boost(&program, 2, setup), 73144);
+        group.bench_function(name, |b| b.iter(|| boost(black_box(&program), 2, setup)));
+    }
+    group.finish();
+}
+
+fn bench_breakout(c: &mut Criterion) {
+    let program = intcode::parse_program(BREAKOUT).unwrap();
+    let score = breakout(&program, engines()[0].1);
+    let mut group = c.benchmark_group("day13 breakout");
+    group.sample_size(10);
+    for (name, setup) in engines() {
+        assert_eq!(breakout(&program, setup), score);
+        group.bench_function(name, |b| b.iter(|| breakout(black_box(&program), setup)));
+    }
+    group.finish();
+}
+
+/// Breakout partway through the game, waiting for the joystick.
+fn breakout_in_progress(program: &[i64]) -> Computer {
+    let mut program = program.to_vec();
+    program[0] = 2;
+    let mut computer = Computer::from_mem(program);
+    for _ in 0..100 {
+        computer.run_to_input().unwrap();
+        computer.push_input(0);
+    }
+    computer.run_to_input().unwrap();
+    computer
+}
+
+fn bench_clone(c: &mut Criterion) {
+    let program = intcode::parse_program(BREAKOUT).unwrap();
+    let computer = breakout_in_progress(&program);
+    let mut memory = computer.memory().to_vec();
+    memory.resize(0x8000, 0);
+    let mut group = c.benchmark_group("clone");
+    // What cloning the memory cost when it was a plain vector of 0x8000 words
+    group.bench_function("vec", |b| b.iter(|| black_box(&memory).clone()));
+    group.bench_function("computer", |b| b.iter(|| black_box(&computer).clone()));
+    // Fork and run to the next prompt, like a search does
+    group.bench_function("fork", |b| {
+        b.iter(|| {
+            let mut fork = black_box(&computer).clone();
+            fork.push_input(1);
+            fork.run_to_input().unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_boost, bench_breakout, bench_clone);
+criterion_main!(benches);
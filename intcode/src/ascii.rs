@@ -0,0 +1,144 @@
+//! Talking to programs in ASCII. Input is given as lines of text, sent one character code at
+//! a time and ended by a newline, and output is put together into lines again. Values that
+//! aren't ASCII, like the answer at the end of a conversation, come through on their own.
+
+use std::collections::VecDeque;
+use std::mem;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::computer::IO;
+use crate::error::EndOfInput;
+
+/// Largest value that is taken to be a character.
+const MAX_ASCII: i64 = 127;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AsciiEvent {
+    /// A line of output, without the newline.
+    Line(String),
+    /// Output that isn't ASCII.
+    Value(i64),
+}
+
+/// ASCII IO over queues: input is taken from the lines pushed with `push_line`, and output
+/// is collected as events. Running out of input ends the program with
+/// `IntcodeError::InputClosed`.
+#[derive(Debug, Clone, Default)]
+pub struct AsciiIO {
+    input: VecDeque<i64>,
+    // the output line so far
+    line: String,
+    events: Vec<AsciiEvent>,
+}
+
+impl AsciiIO {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `line` as input, followed by a newline.
+    pub fn push_line(&mut self, line: &str) {
+        self.input.extend(encode(line));
+    }
+
+    /// Handle one value of output.
+    pub fn receive(&mut self, value: i64) {
+        match value {
+            10 => {
+                let line = mem::take(&mut self.line);
+                self.events.push(AsciiEvent::Line(line));
+            }
+            0..=MAX_ASCII => self.line.push(value as u8 as char),
+            _ => self.events.push(AsciiEvent::Value(value)),
+        }
+    }
+
+    /// End the current output line, if anything has been written to it, like a prompt
+    /// waiting for input on the same line.
+    pub fn flush(&mut self) {
+        if !self.line.is_empty() {
+            self.receive(10);
+        }
+    }
+
+    /// The output since the last call.
+    pub fn take_events(&mut self) -> Vec<AsciiEvent> {
+        mem::take(&mut self.events)
+    }
+}
+
+#[async_trait]
+impl IO for AsciiIO {
+    async fn input(&mut self) -> Result<i64> {
+        Ok(self.input.pop_front().ok_or(EndOfInput)?)
+    }
+
+    async fn output(&mut self, data: i64) -> Result<()> {
+        self.receive(data);
+        Ok(())
+    }
+}
+
+/// The character codes of `line` followed by a newline, as a program reads them.
+pub fn encode(line: &str) -> Vec<i64> {
+    line.chars().chain(Some('\n')).map(|c| c as i64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Computer;
+
+    #[async_std::test]
+    async fn echo_lines() -> Result<()> {
+        // Echo input until a newline, then output 1000 plus the number of characters read
+        let program = crate::asm::assemble(
+            "
+            start:
+                INPUT &char
+                OUTPUT &char
+                ADD &count 1 => &count
+                IF &char == 10 => &done
+                IF NOT &done JUMP TO start
+                OUTPUT &count
+                HALT
+            char: DATA 0
+            count: DATA 1000
+            done: DATA 0
+            ",
+        )?;
+        let mut io = AsciiIO::new();
+        io.push_line("hi");
+        let mut computer = Computer::from_mem(program).with_io(io);
+        computer.run().await?;
+        let mut io = computer.into_io();
+        assert_eq!(
+            io.take_events(),
+            vec![AsciiEvent::Line("hi".to_string()), AsciiEvent::Value(1003)]
+        );
+        assert_eq!(io.take_events(), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn partial_lines() {
+        let mut io = AsciiIO::new();
+        for value in encode("Command?") {
+            io.receive(value);
+        }
+        io.receive('>' as i64);
+        io.receive(1000);
+        io.flush();
+        io.flush();
+        assert_eq!(
+            io.take_events(),
+            vec![
+                AsciiEvent::Line("Command?".to_string()),
+                AsciiEvent::Value(1000),
+                AsciiEvent::Line(">".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Assembler for the textual syntax printed by `Instruction`'s `Display` and the
+//! disassembler, e.g.
+//!
+//! ```text
+//! # Output the input, doubled
+//! start:
+//!     INPUT &value
+//!     MUL &value 2 => &value
+//!     OUTPUT &value
+//!     IF 1 JUMP TO start
+//! value:
+//!     DATA 0
+//! ```
+//!
+//! Parameters are `&addr` (position), `n` (immediate) or `base + n` (relative). Addresses
+//! and immediates can be labels. Where ADD, MUL and INPUT store, `Display` puts another `&`
+//! in front, like `=> &&6` or `INPUT &base + 1`, and that is read the same as without it.
+//! A line can start with a numeric address like `42:`, which is checked against where the
+//! line actually ends up.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Pos(Value),
+    Immediate(Value),
+    Relative(i64),
+}
+
+impl Operand {
+    fn mode(&self) -> i64 {
+        match self {
+            Operand::Pos(_) => 0,
+            Operand::Immediate(_) => 1,
+            Operand::Relative(_) => 2,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Operand::Pos(value) | Operand::Immediate(value) => value,
+            Operand::Relative(offset) => Value::Number(offset),
+        }
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>> {
+    let mut words = Vec::new();
+    let mut labels = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        parse_line(line, &mut words, &mut labels)
+            .with_context(|| format!("Line {}: {}", index + 1, line.trim()))?;
+    }
+
+    words
+        .into_iter()
+        .map(|word| match word {
+            Value::Number(value) => Ok(value),
+            Value::Label(label) => labels
+                .get(&label)
+                .map(|&addr| addr as i64)
+                .ok_or_else(|| anyhow!("Unknown label {}", label)),
+        })
+        .collect()
+}
+
+fn parse_line(
+    line: &str,
+    words: &mut Vec<Value>,
+    labels: &mut HashMap<String, usize>,
+) -> Result<()> {
+    let line = line.split(['#', ';']).next().unwrap_or("");
+    let line = line.replace(',', " , ");
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+
+    // Labels and address checks
+    while let Some(name) = tokens.first().and_then(|token| token.strip_suffix(':')) {
+        if let Ok(addr) = name.parse::<usize>() {
+            if addr != words.len() {
+                bail!("Expected address {}, but this is at {}", addr, words.len());
+            }
+        } else if is_identifier(name) {
+            if labels.insert(name.to_string(), words.len()).is_some() {
+                bail!("Label {} defined twice", name);
+            }
+        } else {
+            bail!("Invalid label {}", name);
+        }
+        tokens.remove(0);
+    }
+
+    if tokens.is_empty() {
+        return Ok(());
+    }
+    let mut tokens = Tokens { tokens, pos: 0 };
+
+    let (op, operands) = match tokens.next()? {
+        "ADD" => (1, tokens.binary()?),
+        "MUL" => (2, tokens.binary()?),
+        "INPUT" => (3, vec![tokens.operand()?]),
+        "OUTPUT" => (4, vec![tokens.operand()?]),
+        "IF" => {
+            if tokens.peek() == Some("NOT") {
+                tokens.next()?;
+                (6, tokens.jump()?)
+            } else {
+                let a = tokens.operand()?;
+                match tokens.next()? {
+                    "JUMP" => {
+                        tokens.expect("TO")?;
+                        (5, vec![a, tokens.operand()?])
+                    }
+                    "<" => (7, tokens.comparison(a)?),
+                    "==" => (8, tokens.comparison(a)?),
+                    other => bail!("Expected JUMP, < or ==, got {}", other),
+                }
+            }
+        }
+        "REL_BASE" => {
+            tokens.expect("+=")?;
+            (9, vec![tokens.operand()?])
+        }
+        "HALT" => (99, vec![]),
+        "DATA" => {
+            loop {
+                words.push(tokens.value()?);
+                if tokens.peek().is_none() {
+                    break;
+                }
+                tokens.expect(",")?;
+            }
+            return Ok(());
+        }
+        other => bail!("Unknown instruction {}", other),
+    };
+    if let Some(token) = tokens.peek() {
+        bail!("Unexpected {}", token);
+    }
+
+    let modes = operands
+        .iter()
+        .enumerate()
+        .map(|(index, operand)| operand.mode() * 10_i64.pow(index as u32 + 2))
+        .sum::<i64>();
+    words.push(Value::Number(op + modes));
+    words.extend(operands.into_iter().map(Operand::into_value));
+    Ok(())
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !["base", "NOT", "JUMP", "TO"].contains(&name)
+}
+
+struct Tokens<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str> {
+        let token = self
+            .peek()
+            .ok_or_else(|| anyhow!("Unexpected end of line"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => bail!("Expected {}, got {}", expected, token),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        parse_value(self.next()?)
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let token = self.next()?;
+        match token.strip_prefix('&') {
+            Some(param) if param == "base" || param.starts_with('&') => self.param(param),
+            _ => self.param(token),
+        }
+    }
+
+    fn param(&mut self, token: &str) -> Result<Operand> {
+        if token == "base" {
+            let sign = match self.peek() {
+                Some("+") => 1,
+                Some("-") => -1,
+                _ => return Ok(Operand::Relative(0)),
+            };
+            self.next()?;
+            let offset = self.next()?;
+            let offset: i64 = offset
+                .parse()
+                .with_context(|| format!("Invalid relative offset {}", offset))?;
+            Ok(Operand::Relative(sign * offset))
+        } else if let Some(addr) = token.strip_prefix('&') {
+            Ok(Operand::Pos(parse_value(addr)?))
+        } else {
+            Ok(Operand::Immediate(parse_value(token)?))
+        }
+    }
+
+    // `a b => result`
+    fn binary(&mut self) -> Result<Vec<Operand>> {
+        let a = self.operand()?;
+        let b = self.operand()?;
+        self.expect("=>")?;
+        Ok(vec![a, b, self.operand()?])
+    }
+
+    // The `b => result` part of `IF a < b => result`
+    fn comparison(&mut self, a: Operand) -> Result<Vec<Operand>> {
+        let b = self.operand()?;
+        self.expect("=>")?;
+        Ok(vec![a, b, self.operand()?])
+    }
+
+    // The `check JUMP TO target` part of an `IF`
+    fn jump(&mut self) -> Result<Vec<Operand>> {
+        let check = self.operand()?;
+        self.expect("JUMP")?;
+        self.expect("TO")?;
+        Ok(vec![check, self.operand()?])
+    }
+}
+
+fn parse_value(token: &str) -> Result<Value> {
+    if is_identifier(token) {
+        return Ok(Value::Label(token.to_string()));
+    }
+    // Position parameters are displayed as usize, so negative addresses come back huge
+    match token.parse::<i64>() {
+        Ok(value) => Ok(Value::Number(value)),
+        Err(_) => Ok(Value::Number(
+            token
+                .parse::<u64>()
+                .with_context(|| format!("Invalid value {}", token))? as i64,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::Instruction;
+    use crate::disasm::disassemble;
+
+    #[test]
+    fn assemble_instructions() -> Result<()> {
+        assert_eq!(
+            assemble(
+                "
+                INPUT &9
+                IF &9 == 8 => &9  # compare
+                OUTPUT &9
+                HALT
+                DATA -1
+                "
+            )?,
+            vec![3, 9, 1008, 9, 8, 9, 4, 9, 99, -1]
+        );
+        assert_eq!(
+            assemble("REL_BASE += 15\nINPUT base + 1\nOUTPUT &16\nHALT")?,
+            vec![109, 15, 203, 1, 4, 16, 99]
+        );
+        assert_eq!(
+            assemble("ADD base - 2 1 => base + -3")?,
+            vec![21201, -2, 1, -3]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn labels() -> Result<()> {
+        let program = assemble(
+            "
+            start:
+                IF NOT &flag JUMP TO end
+                IF 1 JUMP TO start
+            flag: DATA 0, end
+            end:
+                HALT
+            ",
+        )?;
+        assert_eq!(program, vec![1006, 6, 8, 1105, 1, 0, 0, 8, 99]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert!(assemble("JUMP 3").is_err());
+        assert!(assemble("IF 1 JUMP TO nowhere").is_err());
+        assert!(assemble("HALT\n0: HALT").is_err());
+        assert!(assemble("ADD 1 2 &3").is_err());
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let program = crate::parse_program(include_str!("../../aoc09/input/input.txt"))?;
+        let listing = disassemble(&program).to_string();
+        assert_eq!(assemble(&listing)?, program);
+
+        let program = vec![21101, 9, 0, 0, 1105, 1, 12, 0, 0, 99, 0, 0, 2106, 0, 0];
+        assert_eq!(assemble(&disassemble(&program).to_string())?, program);
+        Ok(())
+    }
+
+    #[test]
+    fn display_output() -> Result<()> {
+        let program = vec![3, 13, 1002, 13, 3, 13, 21201, 13, 1, 0, 203, 1, 4, 13, 99];
+        let mut listing = String::new();
+        let mut pc = 0;
+        while pc < program.len() {
+            let instr = Instruction::decode(&program, pc)?;
+            listing += &format!("{}\n", instr);
+            pc += instr.len();
+        }
+        assert!(listing.starts_with("INPUT &&13\nMUL &13 3 => &&13\n"));
+        assert!(listing.contains("=> &base + 0\nINPUT &base + 1\n"));
+        assert_eq!(assemble(&listing)?, program);
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use anyhow::{anyhow, bail, Result};
+
+use intcode::ascii::{encode, AsciiEvent, AsciiIO};
+use intcode::{Computer, StopReason};
+
+const USAGE: &str = "Usage: intcode-ascii <program>";
+
+fn main() -> Result<()> {
+    let path = env::args().nth(1).ok_or_else(|| anyhow!(USAGE))?;
+    let program = intcode::parse_program(&fs::read_to_string(&path)?)?;
+    let mut computer = Computer::from_mem(program);
+    let mut ascii = AsciiIO::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        let reason = computer.resume()?;
+        match reason {
+            StopReason::ProducedOutput(value) => ascii.receive(value),
+            // Show prompts before waiting for input, and whatever is left at the end
+            StopReason::NeedsInput | StopReason::Halted => ascii.flush(),
+            _ => {}
+        }
+        for event in ascii.take_events() {
+            match event {
+                AsciiEvent::Line(line) => println!("{}", line),
+                AsciiEvent::Value(value) => println!("[value] {}", value),
+            }
+        }
+        match reason {
+            StopReason::ProducedOutput(_) => {}
+            StopReason::NeedsInput => {
+                print!("> ");
+                io::stdout().flush()?;
+                match lines.next() {
+                    Some(line) => encode(&line?)
+                        .into_iter()
+                        .for_each(|value| computer.push_input(value)),
+                    None => {
+                        eprintln!("End of input, stopping");
+                        break;
+                    }
+                }
+            }
+            StopReason::Halted => break,
+            reason => bail!("Unexpected stop: {:?}", reason),
+        }
+    }
+    Ok(())
+}
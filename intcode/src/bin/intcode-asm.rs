@@ -0,0 +1,24 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+
+use anyhow::Result;
+
+use intcode::asm::assemble;
+
+fn main() -> Result<()> {
+    let source = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            source
+        }
+    };
+    let program = assemble(&source)?;
+
+    let words: Vec<String> = program.iter().map(|word| word.to_string()).collect();
+    println!("{}", words.join(","));
+
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use std::env;
+use std::fs;
+use std::io::{self, prelude::*};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use intcode::breakpoints::{Access, Condition};
+use intcode::snapshot::Snapshot;
+use intcode::{Computer, StopReason};
+
+const HELP: &str = "\
+Commands:
+  s, step [n]                 Execute n instructions (default 1)
+  c, continue                 Run until a breakpoint, output, input or halt
+  b, break <addr> [if <cond>] Break at addr, optionally only when cond holds
+  b, break if <cond>          Break as soon as cond holds
+  d, delete <addr>            Remove the breakpoint at addr
+  w, watch <addr> [r|w|rw]    Break before an instruction reads/writes addr
+  unwatch <addr> [r|w|rw]     Remove a watchpoint
+  info                        List breakpoints and watchpoints
+  i, input <values...>        Queue input values
+  x, mem <addr> [len]         Show memory
+  set <addr> <value>          Change memory
+  r, regs                     Show pc and relative base
+  l, list [addr] [n]          Disassemble n instructions from addr (default pc)
+  save <file>                 Save a snapshot of the computer
+  load <file>                 Restore a snapshot, keeping breakpoints
+  h, help                     Show this help
+  q, quit                     Exit
+
+A condition is `<location> <comparison> <value>`, where location is pc, base or &addr,
+e.g. `&1000 == 2`. An empty line repeats the last command.";
+
+const USAGE: &str = "Usage: intcode-dbg <program> | intcode-dbg --resume <snapshot>";
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (mut computer, path) = match &args[..] {
+        [flag, path] if flag == "--resume" => {
+            let snapshot = Snapshot::load(path)
+                .with_context(|| format!("Could not load snapshot {}", path))?;
+            (Computer::from_snapshot(snapshot), path)
+        }
+        [path] => {
+            let program = intcode::parse_program(&fs::read_to_string(path)?)?;
+            (Computer::from_mem(program), path)
+        }
+        _ => bail!(USAGE),
+    };
+
+    println!("Loaded {}. Type `help` for a list of commands.", path);
+    show_location(&computer);
+
+    let stdin = io::stdin();
+    let mut last_command = String::new();
+    loop {
+        print!("(dbg) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let line = match line.trim() {
+            "" => last_command.clone(),
+            line => line.to_string(),
+        };
+        match command(&mut computer, &line) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(err) => println!("Error: {:#}", err),
+        }
+        last_command = line;
+    }
+}
+
+/// Run one command. Returns true if the debugger should exit.
+fn command(computer: &mut Computer, line: &str) -> Result<bool> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, args) = match words.split_first() {
+        Some((command, args)) => (*command, args),
+        None => return Ok(false),
+    };
+    match command {
+        "s" | "step" => {
+            let n = args.first().map(|n| n.parse()).transpose()?.unwrap_or(1);
+            for _ in 0..n {
+                match computer.step()? {
+                    None => {}
+                    Some(reason @ StopReason::ProducedOutput(_)) => show_stop(reason),
+                    Some(reason) => {
+                        show_stop(reason);
+                        break;
+                    }
+                }
+            }
+            show_location(computer);
+        }
+        "c" | "continue" => {
+            let reason = computer.resume()?;
+            show_stop(reason);
+            show_location(computer);
+        }
+        "b" | "break" => match args {
+            ["if", condition @ ..] => {
+                let condition: Condition = condition.join(" ").parse()?;
+                computer.breakpoints_mut().break_when(condition);
+            }
+            [addr] => computer.breakpoints_mut().add(parse_addr(addr)?),
+            [addr, "if", condition @ ..] => {
+                let condition: Condition = condition.join(" ").parse()?;
+                computer
+                    .breakpoints_mut()
+                    .add_conditional(parse_addr(addr)?, condition);
+            }
+            _ => bail!("Usage: break <addr> [if <cond>] | break if <cond>"),
+        },
+        "d" | "delete" => {
+            let addr = parse_addr(
+                args.first()
+                    .ok_or_else(|| anyhow!("Usage: delete <addr>"))?,
+            )?;
+            if !computer.breakpoints_mut().remove(addr) {
+                bail!("No breakpoint at {}", addr);
+            }
+        }
+        "w" | "watch" | "unwatch" => {
+            let addr = parse_addr(
+                args.first()
+                    .ok_or_else(|| anyhow!("Usage: {} <addr> [r|w|rw]", command))?,
+            )?;
+            let accesses = match args.get(1).copied().unwrap_or("rw") {
+                "r" => vec![Access::Read],
+                "w" => vec![Access::Write],
+                "rw" => vec![Access::Read, Access::Write],
+                other => bail!("Expected r, w or rw, got {}", other),
+            };
+            for access in accesses {
+                if command == "unwatch" {
+                    computer.breakpoints_mut().unwatch(addr, access);
+                } else {
+                    computer.breakpoints_mut().watch(addr, access);
+                }
+            }
+        }
+        "info" => {
+            let breakpoints = computer.breakpoints();
+            for (pc, condition) in breakpoints.pcs() {
+                match condition {
+                    Some(condition) => println!("break {} if {}", pc, condition),
+                    None => println!("break {}", pc),
+                }
+            }
+            for condition in breakpoints.conditions() {
+                println!("break if {}", condition);
+            }
+            for (addr, access) in breakpoints.watched() {
+                println!("watch {} {:?}", addr, access);
+            }
+        }
+        "i" | "input" => {
+            for value in args {
+                computer.push_input(
+                    value
+                        .parse()
+                        .with_context(|| format!("Invalid input {}", value))?,
+                );
+            }
+        }
+        "x" | "mem" => {
+            let addr = parse_addr(
+                args.first()
+                    .ok_or_else(|| anyhow!("Usage: mem <addr> [len]"))?,
+            )?;
+            let len: usize = args.get(1).map(|n| n.parse()).transpose()?.unwrap_or(8);
+            let limit = computer.memory().limit();
+            if addr >= limit {
+                bail!("{} is past the memory limit", addr);
+            }
+            let end = addr.saturating_add(len).min(limit);
+            for row in (addr..end).step_by(8) {
+                let values: Vec<String> = (row..(row + 8).min(end))
+                    .map(|addr| computer.memory().get(addr).unwrap_or(0).to_string())
+                    .collect();
+                println!("{:>6}: {}", row, values.join(" "));
+            }
+        }
+        "set" => match args {
+            [addr, value] => {
+                let addr = parse_addr(addr)?;
+                *computer
+                    .memory_mut()
+                    .get_mut(addr)
+                    .ok_or_else(|| anyhow!("{} is past the memory limit", addr))? =
+                    value.parse()?;
+            }
+            _ => bail!("Usage: set <addr> <value>"),
+        },
+        "r" | "regs" => println!("pc={} base={}", computer.pc(), computer.relative_base()),
+        "l" | "list" => {
+            let mut addr = args
+                .first()
+                .map(|addr| parse_addr(addr))
+                .transpose()?
+                .unwrap_or(computer.pc());
+            let n: usize = args.get(1).map(|n| n.parse()).transpose()?.unwrap_or(10);
+            for _ in 0..n {
+                match computer.decode(addr) {
+                    Ok(instr) => {
+                        println!("{:>6}: {}", addr, instr);
+                        addr += instr.len();
+                    }
+                    Err(_) => {
+                        println!(
+                            "{:>6}: DATA {}",
+                            addr,
+                            computer.memory().get(addr).unwrap_or(0)
+                        );
+                        addr += 1;
+                    }
+                }
+            }
+        }
+        "save" | "load" => {
+            let path = args
+                .first()
+                .ok_or_else(|| anyhow!("Usage: {} <file>", command))?;
+            if command == "save" {
+                computer.snapshot().save(path)?;
+                println!("Saved to {}", path);
+            } else {
+                computer.restore(Snapshot::load(path)?);
+                show_location(computer);
+            }
+        }
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Ok(true),
+        other => bail!("Unknown command {}, try `help`", other),
+    }
+    Ok(false)
+}
+
+fn parse_addr(addr: &str) -> Result<usize> {
+    addr.trim_start_matches('&')
+        .parse()
+        .with_context(|| format!("Invalid address {}", addr))
+}
+
+fn show_stop(reason: StopReason) {
+    match reason {
+        StopReason::Halted => println!("Program halted"),
+        StopReason::NeedsInput => println!("Program wants input, queue some with `input`"),
+        StopReason::ProducedOutput(value) => println!("Output: {}", value),
+        StopReason::Breakpoint => println!("Breakpoint"),
+        StopReason::Watchpoint { address, access } => {
+            println!("Watchpoint: {:?} of {}", access, address)
+        }
+    }
+}
+
+fn show_location(computer: &Computer) {
+    let instr = match computer.decode(computer.pc()) {
+        Ok(instr) => instr.to_string(),
+        Err(err) => format!("<{}>", err),
+    };
+    println!(
+        "pc={} base={}  {:>6}: {}",
+        computer.pc(),
+        computer.relative_base(),
+        computer.pc(),
+        instr
+    );
+}
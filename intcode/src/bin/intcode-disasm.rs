@@ -0,0 +1,51 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+
+use anyhow::Result;
+
+use intcode::cfg::Cfg;
+use intcode::disasm::{disassemble, label};
+use intcode::functions::{annotate, functions};
+
+fn main() -> Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let dot = args.iter().any(|arg| arg == "--dot");
+    let summary = args.iter().any(|arg| arg == "--functions");
+    args.retain(|arg| arg != "--dot" && arg != "--functions");
+
+    let input = match args.first() {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+    let program = intcode::parse_program(&input)?;
+
+    let mut disasm = disassemble(&program);
+    let cfg = Cfg::from_disassembly(&disasm);
+    let functions = functions(&cfg);
+    if dot {
+        print!("{}", cfg.to_dot());
+    } else if summary {
+        for function in functions.values() {
+            let callees: BTreeSet<String> = function.calls.values().map(|&to| label(to)).collect();
+            print!("{}, {} blocks", function, function.blocks.len());
+            if !callees.is_empty() {
+                print!(
+                    ", calls {}",
+                    callees.into_iter().collect::<Vec<_>>().join(", ")
+                );
+            }
+            println!();
+        }
+    } else {
+        annotate(&mut disasm, &functions);
+        print!("{}", disasm);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use intcode::{Computer, StopReason};
+
+const USAGE: &str = "Usage: intcode-prof <program> [--top n] [--folded <file>] [inputs...]";
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let path = args.next().ok_or_else(|| anyhow!(USAGE))?;
+    let program = intcode::parse_program(&fs::read_to_string(&path)?)?;
+    let mut computer = Computer::from_mem(program.clone());
+    let mut top = 20;
+    let mut folded = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => top = args.next().ok_or_else(|| anyhow!(USAGE))?.parse()?,
+            "--folded" => folded = Some(args.next().ok_or_else(|| anyhow!(USAGE))?),
+            input => computer.push_input(
+                input
+                    .parse()
+                    .with_context(|| format!("Invalid input {}", input))?,
+            ),
+        }
+    }
+
+    computer.enable_profile();
+    loop {
+        match computer.resume()? {
+            StopReason::ProducedOutput(value) => println!("Output: {}", value),
+            StopReason::NeedsInput => {
+                eprintln!("Program wants more input, stopping");
+                break;
+            }
+            StopReason::Halted => break,
+            reason => bail!("Unexpected stop: {:?}", reason),
+        }
+    }
+
+    let profile = computer.take_profile().unwrap_or_default();
+    // Annotate with the original program, code that modifies itself shows up as it was loaded
+    println!("\n{}", profile.report(&program, top));
+    if let Some(folded) = folded {
+        profile.write_folded(BufWriter::new(File::create(&folded)?))?;
+        println!("Wrote folded stacks to {}", folded);
+    }
+    Ok(())
+}
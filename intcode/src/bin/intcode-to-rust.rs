@@ -0,0 +1,31 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+
+use anyhow::Result;
+
+use intcode::overflow::Overflow;
+use intcode::transpile::transpile_with_overflow;
+
+fn main() -> Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut overflow = Overflow::default();
+    for arg in &args {
+        if let Some(policy) = arg.strip_prefix("--overflow=") {
+            overflow = policy.parse()?;
+        }
+    }
+    args.retain(|arg| !arg.starts_with("--overflow="));
+
+    let input = match args.first() {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+    let program = intcode::parse_program(&input)?;
+    print!("{}", transpile_with_overflow(&program, overflow)?);
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use std::env;
+use std::fs;
+
+use anyhow::{bail, Context, Result};
+
+use intcode::trace::{replay, Trace};
+use intcode::{Computer, StopReason};
+
+const USAGE: &str = "\
+Usage:
+  intcode-trace record <program> <trace> [inputs...]
+  intcode-trace replay <program> <trace>";
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match &args[..] {
+        [command, program, trace, inputs @ ..] if command == "record" => {
+            let program = intcode::parse_program(&fs::read_to_string(program)?)?;
+            let mut computer = Computer::from_mem(program);
+            computer.enable_trace();
+            for input in inputs {
+                computer.push_input(
+                    input
+                        .parse()
+                        .with_context(|| format!("Invalid input {}", input))?,
+                );
+            }
+            let result = loop {
+                match computer.resume() {
+                    Ok(StopReason::ProducedOutput(value)) => println!("{}", value),
+                    Ok(StopReason::NeedsInput) => {
+                        eprintln!("Program wants more input, stopping");
+                        break Ok(());
+                    }
+                    Ok(_) => break Ok(()),
+                    Err(err) => break Err(err),
+                }
+            };
+            // Save what we have even if the program failed, that is when a trace is useful
+            let recorded = computer.take_trace().unwrap_or_default();
+            recorded.save(trace)?;
+            eprintln!("Recorded {} steps to {}", recorded.events.len(), trace);
+            result?;
+        }
+        [command, program, trace] if command == "replay" => {
+            let program = intcode::parse_program(&fs::read_to_string(program)?)?;
+            let trace = Trace::load(trace)?;
+            match replay(&program, &trace) {
+                None => println!("Replayed {} steps, no divergence", trace.events.len()),
+                Some(divergence) => {
+                    println!("{}", divergence);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::memory::Memory;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Something a `Condition` can look at.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Location {
+    Pc,
+    RelativeBase,
+    Memory(usize),
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// A predicate like `&1000 == 2` or `base > 100`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Condition {
+    pub location: Location,
+    pub comparison: Comparison,
+    pub value: i64,
+}
+
+impl Condition {
+    pub fn holds(&self, pc: usize, relative_base: i64, memory: &Memory) -> bool {
+        let actual = match self.location {
+            Location::Pc => pc as i64,
+            Location::RelativeBase => relative_base,
+            Location::Memory(addr) => match memory.get(addr) {
+                Some(value) => value,
+                None => return false,
+            },
+        };
+        use Comparison::*;
+        match self.comparison {
+            Equal => actual == self.value,
+            NotEqual => actual != self.value,
+            Less => actual < self.value,
+            LessOrEqual => actual <= self.value,
+            Greater => actual > self.value,
+            GreaterOrEqual => actual >= self.value,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    /// Parse `<location> <comparison> <value>`, where location is `pc`, `base` or `&addr`.
+    fn from_str(s: &str) -> anyhow::Result<Condition> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let (location, comparison, value) = match parts[..] {
+            [location, comparison, value] => (location, comparison, value),
+            _ => bail!("Expected `<location> <comparison> <value>`, got `{}`", s),
+        };
+        let location = match location {
+            "pc" => Location::Pc,
+            "base" => Location::RelativeBase,
+            addr => Location::Memory(
+                addr.strip_prefix('&')
+                    .ok_or_else(|| anyhow!("Expected pc, base or &addr, got {}", addr))?
+                    .parse()
+                    .with_context(|| format!("Invalid address {}", addr))?,
+            ),
+        };
+        use Comparison::*;
+        let comparison = match comparison {
+            "==" => Equal,
+            "!=" => NotEqual,
+            "<" => Less,
+            "<=" => LessOrEqual,
+            ">" => Greater,
+            ">=" => GreaterOrEqual,
+            other => bail!("Unknown comparison {}", other),
+        };
+        let value = value
+            .parse()
+            .with_context(|| format!("Invalid value {}", value))?;
+        Ok(Condition {
+            location,
+            comparison,
+            value,
+        })
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Location::Pc => write!(f, "pc")?,
+            Location::RelativeBase => write!(f, "base")?,
+            Location::Memory(addr) => write!(f, "&{}", addr)?,
+        }
+        use Comparison::*;
+        let comparison = match self.comparison {
+            Equal => "==",
+            NotEqual => "!=",
+            Less => "<",
+            LessOrEqual => "<=",
+            Greater => ">",
+            GreaterOrEqual => ">=",
+        };
+        write!(f, " {} {}", comparison, self.value)
+    }
+}
+
+/// Breakpoints and watchpoints for a `Computer`. They are checked before each instruction
+/// by `Computer::resume` and `Computer::run_until`, but never for the instruction execution
+/// resumes at, so resuming always makes progress.
+///
+/// `Computer::run`, and so `Computer::spawn`, ignore them, as they can only stop when the
+/// program halts or fails. `run_to_input` and `run_with_inputs` continue past them.
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    // pc breakpoints, with an optional condition
+    pcs: BTreeMap<usize, Option<Condition>>,
+    // conditions checked before every instruction
+    conditions: Vec<Condition>,
+    reads: BTreeSet<usize>,
+    writes: BTreeSet<usize>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pcs.is_empty()
+            && self.conditions.is_empty()
+            && self.reads.is_empty()
+            && self.writes.is_empty()
+    }
+
+    /// Break before executing the instruction at `pc`.
+    pub fn add(&mut self, pc: usize) {
+        self.pcs.insert(pc, None);
+    }
+
+    /// Break before executing the instruction at `pc`, if `condition` holds.
+    pub fn add_conditional(&mut self, pc: usize, condition: Condition) {
+        self.pcs.insert(pc, Some(condition));
+    }
+
+    /// Break as soon as `condition` holds, wherever the program is.
+    pub fn break_when(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    /// Break before an instruction that accesses `addr`.
+    pub fn watch(&mut self, addr: usize, access: Access) {
+        match access {
+            Access::Read => self.reads.insert(addr),
+            Access::Write => self.writes.insert(addr),
+        };
+    }
+
+    /// Remove the breakpoint at `pc`. Returns false if there was none.
+    pub fn remove(&mut self, pc: usize) -> bool {
+        self.pcs.remove(&pc).is_some()
+    }
+
+    pub fn unwatch(&mut self, addr: usize, access: Access) -> bool {
+        match access {
+            Access::Read => self.reads.remove(&addr),
+            Access::Write => self.writes.remove(&addr),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn pcs(&self) -> impl Iterator<Item = (usize, Option<&Condition>)> {
+        self.pcs
+            .iter()
+            .map(|(&pc, condition)| (pc, condition.as_ref()))
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn watched(&self) -> impl Iterator<Item = (usize, Access)> + '_ {
+        let reads = self.reads.iter().map(|&addr| (addr, Access::Read));
+        let writes = self.writes.iter().map(|&addr| (addr, Access::Write));
+        reads.chain(writes)
+    }
+
+    /// Whether a breakpoint (not a watchpoint) triggers before the instruction at `pc`.
+    pub(crate) fn breaks_at(&self, pc: usize, relative_base: i64, memory: &Memory) -> bool {
+        let at_pc = match self.pcs.get(&pc) {
+            Some(None) => true,
+            Some(Some(condition)) => condition.holds(pc, relative_base, memory),
+            None => false,
+        };
+        at_pc
+            || self
+                .conditions
+                .iter()
+                .any(|condition| condition.holds(pc, relative_base, memory))
+    }
+
+    pub(crate) fn is_watched(&self, addr: usize, access: Access) -> bool {
+        match access {
+            Access::Read => self.reads.contains(&addr),
+            Access::Write => self.writes.contains(&addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, StopReason};
+
+    #[test]
+    fn parse_condition() -> anyhow::Result<()> {
+        let condition: Condition = "&1000 >= -3".parse()?;
+        assert_eq!(
+            condition,
+            Condition {
+                location: Location::Memory(1000),
+                comparison: Comparison::GreaterOrEqual,
+                value: -3,
+            }
+        );
+        assert_eq!(condition.to_string(), "&1000 >= -3");
+        assert_eq!("base != 7".parse::<Condition>()?.to_string(), "base != 7");
+        assert!("pc = 1".parse::<Condition>().is_err());
+        assert!("1000 == 1".parse::<Condition>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn break_at_pc() -> anyhow::Result<()> {
+        let mut computer = Computer::from_mem(vec![1101, 1, 1, 0, 1101, 2, 2, 0, 99]);
+        computer.breakpoints_mut().add(4);
+        assert_eq!(computer.resume()?, StopReason::Breakpoint);
+        assert_eq!(computer.pc(), 4);
+        assert_eq!(computer.resume()?, StopReason::Halted);
+        Ok(())
+    }
+
+    #[test]
+    fn conditional_breakpoints() -> anyhow::Result<()> {
+        // Count memory[20] up to 5
+        let program = vec![1001, 20, 1, 20, 1007, 20, 5, 21, 1005, 21, 0, 99];
+        let mut computer = Computer::from_mem(program.clone());
+        computer.breakpoints_mut().add_conditional(
+            4,
+            Condition {
+                location: Location::Memory(20),
+                comparison: Comparison::Equal,
+                value: 3,
+            },
+        );
+        assert_eq!(computer.resume()?, StopReason::Breakpoint);
+        assert_eq!(computer.pc(), 4);
+        assert_eq!(computer.resume()?, StopReason::Halted);
+
+        let mut computer = Computer::from_mem(program);
+        computer.breakpoints_mut().break_when(Condition {
+            location: Location::Memory(21),
+            comparison: Comparison::Equal,
+            value: 1,
+        });
+        assert_eq!(computer.resume()?, StopReason::Breakpoint);
+        assert_eq!(computer.pc(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn watchpoints() -> anyhow::Result<()> {
+        let mut computer = Computer::from_mem(vec![1101, 1, 1, 9, 1001, 9, 1, 10, 99, 0, 0]);
+        computer.breakpoints_mut().watch(9, Access::Read);
+        computer.breakpoints_mut().watch(10, Access::Write);
+        assert_eq!(
+            computer.resume()?,
+            StopReason::Watchpoint {
+                address: 9,
+                access: Access::Read
+            }
+        );
+        assert_eq!(computer.pc(), 4);
+        computer.breakpoints_mut().unwatch(9, Access::Read);
+        // The instruction we stopped at is not checked again
+        assert_eq!(computer.resume()?, StopReason::Halted);
+
+        let mut computer = Computer::from_mem(vec![1101, 1, 1, 9, 1001, 9, 1, 10, 99, 0, 0]);
+        computer.breakpoints_mut().watch(10, Access::Write);
+        assert_eq!(
+            computer.resume()?,
+            StopReason::Watchpoint {
+                address: 10,
+                access: Access::Write
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+/// How often the clock is looked at, in instructions. Reading it on every instruction would
+/// slow down the interpreter noticeably. Must be a power of two, as `Meter::check` masks the
+/// step count with it.
+const CLOCK_INTERVAL: u64 = 1024;
+const _: () = assert!(CLOCK_INTERVAL.is_power_of_two());
+
+/// Limits on how much a program may do before it is stopped with
+/// `IntcodeError::BudgetExceeded`, so that a program stuck in a loop fails instead of
+/// hanging. Everything counts from when the budget is given to `Computer::set_budget`.
+///
+/// The time limit is wall-clock time, but it is only checked while the program runs: a
+/// computer waiting for input that never comes waits forever.
+///
+/// The clock is only read every `CLOCK_INTERVAL` instructions, so a program can overshoot
+/// its time limit by that many instructions, plus however long it spent waiting for IO in
+/// between.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct Budget {
+    pub steps: Option<u64>,
+    pub time: Option<Duration>,
+    pub output: Option<u64>,
+}
+
+impl Budget {
+    /// No limits at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `steps` instructions.
+    pub fn steps(mut self, steps: u64) -> Self {
+        self.steps = Some(steps);
+        self
+    }
+
+    /// Allow running for at most `time`.
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Allow at most `output` values to be output.
+    pub fn output(mut self, output: u64) -> Self {
+        self.output = Some(output);
+        self
+    }
+}
+
+/// The limit a program ran into.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Limit {
+    Steps(u64),
+    Time(Duration),
+    Output(u64),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Steps(steps) => write!(f, "{} instructions", steps),
+            Limit::Time(time) => write!(f, "{:?}", time),
+            Limit::Output(output) => write!(f, "{} outputs", output),
+        }
+    }
+}
+
+/// Keeps track of what a computer has used of its budget.
+#[derive(Debug, Clone)]
+pub(crate) struct Meter {
+    budget: Budget,
+    // the step count of the computer when it got the budget
+    start_steps: u64,
+    started: Instant,
+    output: u64,
+}
+
+impl Meter {
+    pub fn new(budget: Budget, steps: u64) -> Meter {
+        Meter {
+            budget,
+            start_steps: steps,
+            started: Instant::now(),
+            output: 0,
+        }
+    }
+
+    /// Check whether another instruction may run, given the computer's step count.
+    pub fn check(&self, steps: u64) -> Option<Limit> {
+        // Restoring a snapshot can turn the step count back
+        let used = steps.saturating_sub(self.start_steps);
+        match self.budget {
+            Budget {
+                steps: Some(max), ..
+            } if used >= max => Some(Limit::Steps(max)),
+            Budget {
+                time: Some(max), ..
+            } if used & (CLOCK_INTERVAL - 1) == 0 && self.started.elapsed() > max => {
+                Some(Limit::Time(max))
+            }
+            _ => None,
+        }
+    }
+
+    /// Count a value being output, and check whether it is allowed.
+    pub fn output(&mut self) -> Option<Limit> {
+        match self.budget.output {
+            Some(max) if self.output >= max => Some(Limit::Output(max)),
+            _ => {
+                self.output += 1;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use crate::computer::Instruction;
+
+/// Entries per page. Like `Memory`, the cache is paged so jumping far away does not
+/// allocate everything in between.
+const PAGE_SIZE: usize = 256;
+
+/// The longest instruction, in words. A write to `addr` can change the instructions that
+/// start up to this many words before it.
+const MAX_INSTRUCTION_LEN: usize = 4;
+
+/// Instructions that have already been decoded, by pc.
+///
+/// The cache knows nothing about memory, so whoever writes to memory has to `invalidate`
+/// the address, otherwise self-modifying programs run stale code. Like memory, pages are
+/// shared between clones until they change.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeCache {
+    pages: Vec<Option<Arc<[Option<Instruction>; PAGE_SIZE]>>>,
+}
+
+impl DecodeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, pc: usize) -> Option<Instruction> {
+        match self.pages.get(pc / PAGE_SIZE) {
+            Some(Some(page)) => page[pc % PAGE_SIZE],
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, pc: usize, instr: Instruction) {
+        let page = pc / PAGE_SIZE;
+        if page >= self.pages.len() {
+            self.pages.resize_with(page + 1, || None);
+        }
+        let page = self.pages[page].get_or_insert_with(|| Arc::new([None; PAGE_SIZE]));
+        Arc::make_mut(page)[pc % PAGE_SIZE] = Some(instr);
+    }
+
+    /// Forget every instruction that covers `addr`.
+    pub fn invalidate(&mut self, addr: usize) {
+        for pc in addr.saturating_sub(MAX_INSTRUCTION_LEN - 1)..=addr {
+            if let Some(Some(page)) = self.pages.get_mut(pc / PAGE_SIZE) {
+                if let Some(instr) = page[pc % PAGE_SIZE] {
+                    if pc + instr.len() > addr {
+                        Arc::make_mut(page)[pc % PAGE_SIZE] = None;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, StopReason};
+
+    #[test]
+    fn invalidate_covering_instructions() {
+        let program = [1101, 1, 2, 3, 99];
+        let mut cache = DecodeCache::new();
+        cache.insert(0, Instruction::decode(&program, 0).unwrap());
+        cache.insert(4, Instruction::Halt);
+        cache.invalidate(5);
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(4).is_some());
+        cache.invalidate(3);
+        assert!(cache.get(0).is_none());
+        assert_eq!(cache.get(4), Some(Instruction::Halt));
+    }
+
+    #[test]
+    fn self_modifying_code() -> anyhow::Result<()> {
+        // OUTPUT 7 / ADD &1 1 => &1 / IF 1 JUMP TO 0, incrementing the output's operand
+        let mut computer = Computer::from_mem(vec![104, 7, 1001, 1, 1, 1, 1105, 1, 0]);
+        assert_eq!(computer.resume()?, StopReason::ProducedOutput(7));
+        assert_eq!(computer.resume()?, StopReason::ProducedOutput(8));
+        // Changes made from outside are seen too
+        *computer.memory_mut().get_mut(1).unwrap() = 42;
+        assert_eq!(computer.resume()?, StopReason::ProducedOutput(43));
+        Ok(())
+    }
+}
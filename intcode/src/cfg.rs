@@ -0,0 +1,273 @@
+//! Control flow graphs. The code found by the disassembler is split into basic blocks, which
+//! are connected by the jumps between them, and can be exported to Graphviz, e.g.
+//!
+//! ```text
+//! intcode-disasm --dot input.txt | dot -Tsvg > cfg.svg
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::computer::{Instruction, Param};
+use crate::disasm::{constant, disassemble, is_unconditional, label, Disassembly};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum EdgeKind {
+    /// A jump with an immediate target.
+    Jump,
+    /// Falling through to the next instruction, including not taking a conditional jump.
+    FallThrough,
+    /// From a call to the code after it, where the called function returns to.
+    CallReturn,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Edge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// One past the last address of the block.
+    pub end: usize,
+    pub instrs: Vec<(usize, Instruction)>,
+    pub successors: Vec<Edge>,
+    /// The block ends with a jump whose target is only known at runtime, like a return.
+    pub indirect: bool,
+}
+
+impl BasicBlock {
+    pub fn last(&self) -> &Instruction {
+        &self.instrs.last().unwrap().1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+}
+
+impl Cfg {
+    pub fn new(program: &[i64]) -> Cfg {
+        Cfg::from_disassembly(&disassemble(program))
+    }
+
+    pub fn from_disassembly(disasm: &Disassembly) -> Cfg {
+        let leaders = leaders(&disasm.code, &disasm.labels);
+        let mut blocks: BTreeMap<usize, BasicBlock> = BTreeMap::new();
+        let mut current: Option<BasicBlock> = None;
+        // What the last instruction stored, to recognize calls
+        let mut last_constant = None;
+
+        for (&pc, &instr) in &disasm.code {
+            let mut block = match current.take() {
+                Some(block) if block.end == pc && !leaders.contains(&pc) => block,
+                previous => {
+                    if let Some(mut previous) = previous {
+                        // Ended by the next block starting, not by a jump
+                        if falls_through(previous.last()) {
+                            previous.successors.push(Edge {
+                                to: previous.end,
+                                kind: EdgeKind::FallThrough,
+                            });
+                        }
+                        blocks.insert(previous.start, previous);
+                    }
+                    BasicBlock {
+                        start: pc,
+                        end: pc,
+                        instrs: Vec::new(),
+                        successors: Vec::new(),
+                        indirect: false,
+                    }
+                }
+            };
+            block.instrs.push((pc, instr));
+            block.end = pc + instr.len();
+
+            if let Instruction::JumpIfTrue { jump_to, .. }
+            | Instruction::JumpIfFalse { jump_to, .. } = instr
+            {
+                match jump_to {
+                    Param::Immediate(target) if target >= 0 => block.successors.push(Edge {
+                        to: target as usize,
+                        kind: EdgeKind::Jump,
+                    }),
+                    _ => block.indirect = true,
+                }
+                // Like the disassembler, take a constant stored right before an
+                // unconditional jump to be the return address of a call
+                match last_constant {
+                    Some(address) if address >= 0 && is_unconditional(&instr) => {
+                        block.successors.push(Edge {
+                            to: address as usize,
+                            kind: EdgeKind::CallReturn,
+                        })
+                    }
+                    _ => {}
+                }
+                if !is_unconditional(&instr) {
+                    block.successors.push(Edge {
+                        to: block.end,
+                        kind: EdgeKind::FallThrough,
+                    });
+                }
+                blocks.insert(block.start, block);
+            } else if instr == Instruction::Halt {
+                blocks.insert(block.start, block);
+            } else {
+                current = Some(block);
+            }
+            last_constant = constant(&instr);
+        }
+        if let Some(block) = current {
+            blocks.insert(block.start, block);
+        }
+
+        // Jumps to addresses that aren't code go nowhere we know of
+        for block in blocks.values_mut() {
+            block
+                .successors
+                .retain(|edge| disasm.code.contains_key(&edge.to));
+        }
+        Cfg { blocks }
+    }
+
+    /// Addresses of the blocks that jump or fall through to `addr`.
+    pub fn predecessors(&self, addr: usize) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|block| block.successors.iter().any(|edge| edge.to == addr))
+            .map(|block| block.start)
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT format. Blocks ending in an indirect jump are red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+        for block in self.blocks.values() {
+            let mut text = format!("{}:\\l", label(block.start));
+            for (pc, instr) in &block.instrs {
+                text += &format!("{:>6}: {}\\l", pc, escape(&instr.to_string()));
+            }
+            let color = if block.indirect { ", color=red" } else { "" };
+            writeln!(
+                dot,
+                "    {} [label=\"{}\"{}];",
+                label(block.start),
+                text,
+                color
+            )
+            .unwrap();
+        }
+        for block in self.blocks.values() {
+            for edge in &block.successors {
+                let style = match edge.kind {
+                    EdgeKind::Jump => "",
+                    EdgeKind::FallThrough => " [style=dashed]",
+                    EdgeKind::CallReturn => " [style=dotted]",
+                };
+                writeln!(
+                    dot,
+                    "    {} -> {}{};",
+                    label(block.start),
+                    label(edge.to),
+                    style
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Addresses a basic block has to start at: the entry point, everything the disassembler
+/// found a label for, code after a jump or a gap, and code whose address the program uses
+/// as a constant, since that is how return addresses look.
+pub(crate) fn leaders(
+    code: &BTreeMap<usize, Instruction>,
+    labels: &BTreeSet<usize>,
+) -> BTreeSet<usize> {
+    let mut leaders = labels.clone();
+    leaders.insert(0);
+    let mut end = 0;
+    for (&pc, instr) in code {
+        if pc != end {
+            leaders.insert(pc);
+        }
+        end = pc + instr.len();
+        if let Instruction::JumpIfTrue { .. } | Instruction::JumpIfFalse { .. } = instr {
+            leaders.insert(end);
+        }
+        if let Some(value) = constant(instr) {
+            leaders.insert(value as usize);
+        }
+    }
+    leaders.retain(|addr| code.contains_key(addr));
+    leaders
+}
+
+/// Whether execution can continue with the next instruction.
+pub(crate) fn falls_through(instr: &Instruction) -> bool {
+    !is_unconditional(instr) && *instr != Instruction::Halt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edges(cfg: &Cfg, start: usize) -> Vec<(usize, EdgeKind)> {
+        cfg.blocks[&start]
+            .successors
+            .iter()
+            .map(|edge| (edge.to, edge.kind))
+            .collect()
+    }
+
+    #[test]
+    fn loop_blocks() {
+        // Count &20 up to 5 and output it
+        let program = [1001, 20, 1, 20, 1007, 20, 5, 21, 1005, 21, 0, 4, 20, 99];
+        let cfg = Cfg::new(&program);
+        assert_eq!(cfg.blocks.keys().copied().collect::<Vec<_>>(), vec![0, 11]);
+        assert_eq!(cfg.blocks[&0].end, 11);
+        assert_eq!(
+            edges(&cfg, 0),
+            vec![(0, EdgeKind::Jump), (11, EdgeKind::FallThrough)]
+        );
+        assert_eq!(edges(&cfg, 11), vec![]);
+        assert_eq!(cfg.predecessors(0), vec![0]);
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        // 0: push return address 9, jump to 12; 9: halt; 12: return
+        let program = [21101, 9, 0, 0, 1105, 1, 12, 0, 0, 99, 0, 0, 2106, 0, 0];
+        let cfg = Cfg::new(&program);
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<_>>(),
+            vec![0, 9, 12]
+        );
+        assert_eq!(
+            edges(&cfg, 0),
+            vec![(12, EdgeKind::Jump), (9, EdgeKind::CallReturn)]
+        );
+        assert!(cfg.blocks[&12].indirect);
+        assert!(!cfg.blocks[&0].indirect);
+
+        let dot = cfg.to_dot();
+        assert!(dot.contains("    L0 -> L12;\n    L0 -> L9 [style=dotted];\n"));
+        assert!(dot.contains(
+            "    L12 [label=\"L12:\\l    12: IF NOT 0 JUMP TO base + 0\\l\", color=red];"
+        ));
+    }
+}
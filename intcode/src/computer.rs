@@ -1,12 +1,25 @@
+use std::collections::VecDeque;
 use std::fmt::{self, Debug, Display};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, Result};
 use async_std::{
     sync::{channel, Receiver, Sender},
     task,
 };
 use async_trait::async_trait;
 
+use crate::breakpoints::{Access, Breakpoints};
+use crate::budget::{Budget, Meter};
+use crate::cache::DecodeCache;
+use crate::error::{EndOfInput, IntcodeError};
+use crate::memory::Memory;
+use crate::overflow::Overflow;
+use crate::pipeline::Watch;
+use crate::profile::Profile;
+use crate::snapshot::Snapshot;
+use crate::threaded::{Backend, Effect, ThreadedCode};
+use crate::trace::{Trace, TraceEvent};
+
 // Opcodes
 // 01 ADD op1 op2 addr
 // 02 MULTIPLY op1 op2 addr
@@ -16,7 +29,7 @@ use async_trait::async_trait;
 
 #[async_trait]
 pub trait IO: Sync + Send + 'static + Debug + Clone {
-    async fn input(&self) -> Result<i64>;
+    async fn input(&mut self) -> Result<i64>;
     async fn output(&mut self, data: i64) -> Result<()>;
 }
 
@@ -24,22 +37,27 @@ pub trait IO: Sync + Send + 'static + Debug + Clone {
 pub struct ChannelIO {
     output_ch: Option<Sender<i64>>,
     input_ch: Option<Receiver<i64>>,
+    // set when running in a `Pipeline`, which looks out for deadlocks
+    watch: Option<Watch>,
 }
 
 #[async_trait]
 impl IO for ChannelIO {
-    async fn input(&self) -> Result<i64> {
-        match &self.input_ch {
-            None => Err(anyhow!("Tried to read from unconnected input"))?,
-            Some(input) => Ok(input
-                .recv()
-                .await
-                .ok_or(anyhow!("Input sender-end dropped"))?),
+    async fn input(&mut self) -> Result<i64> {
+        match (&self.input_ch, &self.watch) {
+            (None, _) => Err(anyhow!("Tried to read from unconnected input"))?,
+            (Some(input), Some(watch)) => watch.input(input).await,
+            (Some(input), None) => Ok(input.recv().await.ok_or(EndOfInput)?),
         }
     }
 
     async fn output(&mut self, data: i64) -> Result<()> {
+        if let Some(watch) = &self.watch {
+            watch.output(data);
+        }
         match &self.output_ch {
+            // A pipeline keeps the output of computers that aren't connected to anything
+            None if self.watch.is_some() => {}
             None => Err(anyhow!("Tried to write to unconnected output"))?,
             Some(output) => output.send(data).await,
         }
@@ -48,30 +66,112 @@ impl IO for ChannelIO {
     }
 }
 
+/// IO from plain queues, for running a program without any tasks or channels: input is
+/// taken from a queue filled up front, and output is collected in a `Vec`. Running out of
+/// input ends the program with `IntcodeError::InputClosed`.
+#[derive(Debug, Clone, Default)]
+pub struct QueueIO {
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+}
+
+impl QueueIO {
+    pub fn new(input: &[i64]) -> QueueIO {
+        QueueIO {
+            input: input.iter().copied().collect(),
+            output: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl IO for QueueIO {
+    async fn input(&mut self) -> Result<i64> {
+        Ok(self.input.pop_front().ok_or(EndOfInput)?)
+    }
+
+    async fn output(&mut self, data: i64) -> Result<()> {
+        self.output.push(data);
+        Ok(())
+    }
+}
+
+/// Why a synchronously driven computer (see `Computer::step`) stopped.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum StopReason {
+    Halted,
+    /// The program wants input and none is queued. Queue some with `push_input` and resume;
+    /// the input instruction is executed again.
+    NeedsInput,
+    ProducedOutput(i64),
+    Breakpoint,
+    /// The instruction at the pc is about to access a watched address.
+    Watchpoint {
+        address: usize,
+        access: Access,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct Computer<IOType = ChannelIO> {
-    memory: Vec<i64>,
+    memory: Memory,
     // instruction pointer, or program counter
     pc: usize,
     io: IOType,
     relative_base: i64,
+    // input queued with `push_input`, consumed before asking `io`
+    input: VecDeque<i64>,
+    breakpoints: Breakpoints,
+    // number of instructions executed
+    steps: u64,
+    budget: Option<Meter>,
+    overflow: Overflow,
+    trace: Option<Trace>,
+    profile: Option<Profile>,
+    // decoded instructions, `None` if caching is turned off
+    cache: Option<DecodeCache>,
+    // translated blocks when using `Backend::Threaded`
+    threaded: Option<ThreadedCode>,
 }
 
 impl Computer<ChannelIO> {
-    pub fn from_mem(mut memory: Vec<i64>) -> Computer<ChannelIO> {
-        memory.resize(0x8000, 0);
-        Computer::from_mem_noresize(memory)
+    /// Create a computer whose memory grows on demand, up to `memory::DEFAULT_LIMIT` words.
+    pub fn from_mem(memory: Vec<i64>) -> Computer<ChannelIO> {
+        Computer::with_memory(Memory::new(memory))
     }
 
+    /// Create a computer that can only address the cells of `memory`.
     pub fn from_mem_noresize(memory: Vec<i64>) -> Computer<ChannelIO> {
+        let limit = memory.len();
+        Computer::with_memory(Memory::with_limit(memory, limit))
+    }
+
+    /// Create an unconnected computer that continues where `snapshot` was taken.
+    pub fn from_snapshot(snapshot: Snapshot) -> Computer<ChannelIO> {
+        let mut computer = Computer::from_mem(Vec::new());
+        computer.restore(snapshot);
+        computer
+    }
+
+    fn with_memory(memory: Memory) -> Computer<ChannelIO> {
         Computer {
             memory,
             pc: 0,
             io: ChannelIO {
                 output_ch: None,
                 input_ch: None,
+                watch: None,
             },
             relative_base: 0,
+            input: VecDeque::new(),
+            breakpoints: Breakpoints::new(),
+            steps: 0,
+            budget: None,
+            overflow: Overflow::default(),
+            trace: None,
+            profile: None,
+            cache: Some(DecodeCache::new()),
+            threaded: default_backend(),
         }
     }
 
@@ -83,6 +183,10 @@ impl Computer<ChannelIO> {
         self.io.output_ch = Some(sender)
     }
 
+    pub(crate) fn set_watch(&mut self, watch: Watch) {
+        self.io.watch = Some(watch)
+    }
+
     pub fn create_input_channel(&mut self) -> Sender<i64> {
         let (sender, receiver) = channel(1);
         self.connect_input(receiver);
@@ -96,6 +200,15 @@ impl Computer<ChannelIO> {
     }
 }
 
+// `INTCODE_BACKEND=threaded` makes every computer use the threaded backend, e.g. to run the
+// whole test suite on it
+fn default_backend() -> Option<ThreadedCode> {
+    match std::env::var("INTCODE_BACKEND").as_deref() {
+        Ok("threaded") => Some(ThreadedCode::new()),
+        _ => None,
+    }
+}
+
 impl<IOType: IO> Computer<IOType> {
     pub fn with_io<NewIO: IO>(self, io: NewIO) -> Computer<NewIO> {
         Computer {
@@ -103,6 +216,15 @@ impl<IOType: IO> Computer<IOType> {
             pc: self.pc,
             io,
             relative_base: self.relative_base,
+            input: self.input,
+            breakpoints: self.breakpoints,
+            steps: self.steps,
+            budget: self.budget,
+            overflow: self.overflow,
+            trace: self.trace,
+            profile: self.profile,
+            cache: self.cache,
+            threaded: self.threaded,
         }
     }
 
@@ -110,6 +232,158 @@ impl<IOType: IO> Computer<IOType> {
         self.io
     }
 
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Mutable access to memory. This throws away the decoded instruction cache, since
+    /// any of the code might change.
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        self.forget_code();
+        &mut self.memory
+    }
+
+    // Throw away everything derived from the code in memory
+    fn forget_code(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
+        if let Some(threaded) = &mut self.threaded {
+            threaded.clear();
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self.threaded {
+            Some(_) => Backend::Threaded,
+            None => Backend::Interpreter,
+        }
+    }
+
+    /// Choose the execution engine used by `run` and `resume`, see `threaded`.
+    pub fn set_backend(&mut self, backend: Backend) {
+        if backend != self.backend() {
+            self.threaded = match backend {
+                Backend::Threaded => Some(ThreadedCode::new()),
+                Backend::Interpreter => None,
+            };
+        }
+    }
+
+    /// Choose what ADD and MULTIPLY do when they overflow. The default is
+    /// `Overflow::Wrapping`.
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        if overflow != self.overflow {
+            self.overflow = overflow;
+            // Translated code has the policy built in
+            self.forget_code();
+        }
+    }
+
+    /// Turn the decoded instruction cache on or off. It is on by default, turning it off is
+    /// mostly useful for comparing performance.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache = if enabled {
+            Some(DecodeCache::new())
+        } else {
+            None
+        };
+    }
+
+    /// Change how many words of memory the program may address. Lowering the limit throws
+    /// away what was stored past it.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        if limit < self.memory.len() {
+            self.forget_code();
+        }
+        self.memory.set_limit(limit);
+    }
+
+    /// Limit what the program may do from now on, see `Budget`. Running out stops it with
+    /// `IntcodeError::BudgetExceeded`, from `run` and `spawn` as well as when stepping.
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = Some(Meter::new(budget, self.steps));
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            relative_base: self.relative_base,
+            memory: self.memory.to_vec(),
+            memory_limit: self.memory.limit(),
+            input: self.input.iter().copied().collect(),
+            steps: self.steps,
+        }
+    }
+
+    /// Replace the state of this computer with `snapshot`, keeping its IO and breakpoints.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.memory = Memory::with_limit(snapshot.memory, snapshot.memory_limit);
+        self.forget_code();
+        self.pc = snapshot.pc;
+        self.relative_base = snapshot.relative_base;
+        self.input = snapshot.input.into();
+        self.steps = snapshot.steps;
+    }
+
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Start recording every instruction that is executed, see `trace::Trace`.
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Trace::new());
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub fn trace_mut(&mut self) -> Option<&mut Trace> {
+        self.trace.as_mut()
+    }
+
+    /// Stop tracing and return what has been recorded.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Start counting executed instructions, see `profile::Profile`.
+    pub fn enable_profile(&mut self) {
+        self.profile = Some(Profile::new());
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Stop profiling and return the profile.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    /// Breakpoints and watchpoints, checked by `resume` and `run_until` but not by `run`.
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
     pub fn debug(&self) {
         let mut debugger = self.clone();
         while let Ok(instr) = debugger.next_instr() {
@@ -117,22 +391,45 @@ impl<IOType: IO> Computer<IOType> {
         }
     }
 
-    fn get(&self, pos: usize) -> Result<i64> {
-        Ok(*self
-            .memory
+    // The instruction currently being executed, for error reporting
+    fn opcode(&self) -> i64 {
+        self.memory.get(self.pc).unwrap_or(0)
+    }
+
+    fn get(&self, pos: usize) -> Result<i64, IntcodeError> {
+        self.memory
             .get(pos)
-            .ok_or_else(|| anyhow!("Tried to read past memory, at {}", pos))?)
+            .ok_or_else(|| IntcodeError::InvalidRead {
+                pc: self.pc,
+                opcode: self.opcode(),
+                address: pos as i64,
+            })
     }
 
-    fn set(&mut self, pos: usize, value: i64) -> Result<()> {
-        *self
-            .memory
-            .get_mut(pos)
-            .ok_or(anyhow!("Tried to write past memory"))? = value;
+    fn set(&mut self, pos: usize, value: i64) -> Result<(), IntcodeError> {
+        let (pc, opcode) = (self.pc, self.opcode());
+        *self.memory.get_mut(pos).ok_or(IntcodeError::InvalidWrite {
+            pc,
+            opcode,
+            address: pos as i64,
+        })? = value;
+        self.wrote(pos);
         Ok(())
     }
 
-    fn get_param(&self, param: Param) -> Result<i64> {
+    // Forget any decoded or translated code at `addr`, after writing to it. Returns true if
+    // translated code changed.
+    fn wrote(&mut self, addr: usize) -> bool {
+        if let Some(cache) = &mut self.cache {
+            cache.invalidate(addr);
+        }
+        match &mut self.threaded {
+            Some(threaded) => threaded.written(addr),
+            None => false,
+        }
+    }
+
+    fn get_param(&self, param: Param) -> Result<i64, IntcodeError> {
         Ok(match param {
             Param::Pos(pos) => self.get(pos)?,
             Param::Immediate(val) => val,
@@ -140,15 +437,18 @@ impl<IOType: IO> Computer<IOType> {
         })
     }
 
-    fn set_param(&mut self, param: Param, value: i64) -> Result<()> {
-        Ok(match param {
-            Param::Pos(pos) => self.set(pos, value)?,
-            Param::Immediate(_) => bail!("Cannot set a immidiate value"),
-            Param::Relative(rel) => self.set((self.relative_base + rel) as usize, value)?,
-        })
+    fn set_param(&mut self, param: Param, value: i64) -> Result<(), IntcodeError> {
+        match param {
+            Param::Pos(pos) => self.set(pos, value),
+            Param::Immediate(_) => Err(IntcodeError::WriteToImmediate {
+                pc: self.pc,
+                opcode: self.opcode(),
+            }),
+            Param::Relative(rel) => self.set((self.relative_base + rel) as usize, value),
+        }
     }
 
-    pub fn spawn(mut self) -> task::JoinHandle<Result<()>> {
+    pub fn spawn(mut self) -> task::JoinHandle<Result<(), IntcodeError>> {
         task::spawn(async move {
             let result = self.run().await;
             if let Err(err) = &result {
@@ -158,168 +458,408 @@ impl<IOType: IO> Computer<IOType> {
         })
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    pub async fn run(&mut self) -> Result<(), IntcodeError> {
         loop {
-            let instr = self.next_instr()?;
-            // println!("{}", instr);
-            use Instruction::*;
-            match instr {
-                Add {
-                    a,
-                    b,
-                    result_location,
-                } => {
-                    self.set_param(result_location, self.get_param(a)? + self.get_param(b)?)?;
+            match self.advance()? {
+                None => {}
+                Some(StopReason::NeedsInput) => {
+                    let value = self
+                        .io
+                        .input()
+                        .await
+                        .map_err(|err| IntcodeError::from_io(self.pc, err))?;
+                    self.push_input(value);
                 }
-                Multiply {
-                    a,
-                    b,
-                    result_location,
-                } => {
-                    self.set_param(result_location, self.get_param(a)? * self.get_param(b)?)?;
+                Some(StopReason::ProducedOutput(value)) => {
+                    // step has already moved past the output instruction
+                    let pc = self.pc;
+                    self.io
+                        .output(value)
+                        .await
+                        .map_err(|err| IntcodeError::from_io(pc, err))?
                 }
-                Input { result_location } => {
-                    self.set_param(result_location, self.io.input().await?)?
+                Some(StopReason::Halted) => return Ok(()),
+                Some(StopReason::Breakpoint) | Some(StopReason::Watchpoint { .. }) => {
+                    unreachable!("step never stops on a breakpoint")
                 }
-                Output { param } => self.io.output(self.get_param(param)?).await?,
-                JumpIfTrue { check, jump_to } => {
-                    if self.get_param(check)? > 0 {
-                        self.pc = self.get_param(jump_to)? as usize;
-                    }
+            }
+        }
+    }
+
+    /// Run until the program needs input that hasn't been queued, or halts. Returns the
+    /// output produced on the way, and `StopReason::NeedsInput` or `StopReason::Halted`.
+    /// Together with cloning, this lets a search try different inputs from the same prompt.
+    pub fn run_to_input(&mut self) -> Result<(Vec<i64>, StopReason), IntcodeError> {
+        let mut output = Vec::new();
+        loop {
+            match self.resume()? {
+                StopReason::ProducedOutput(value) => output.push(value),
+                reason @ StopReason::NeedsInput | reason @ StopReason::Halted => {
+                    return Ok((output, reason))
                 }
-                JumpIfFalse { check, jump_to } => {
-                    if self.get_param(check)? == 0 {
-                        self.pc = self.get_param(jump_to)? as usize;
+                StopReason::Breakpoint | StopReason::Watchpoint { .. } => {}
+            }
+        }
+    }
+
+    /// Run the program to the end with `inputs` queued, and return everything it output.
+    /// This does not use the IO, so nothing has to be connected. Running out of input is an
+    /// `IntcodeError::InputClosed`.
+    pub fn run_with_inputs(&mut self, inputs: &[i64]) -> Result<Vec<i64>, IntcodeError> {
+        self.input.extend(inputs);
+        let mut output = Vec::new();
+        loop {
+            match self.resume()? {
+                StopReason::ProducedOutput(value) => output.push(value),
+                StopReason::Halted => return Ok(output),
+                StopReason::NeedsInput => return Err(IntcodeError::InputClosed { pc: self.pc }),
+                StopReason::Breakpoint | StopReason::Watchpoint { .. } => {}
+            }
+        }
+    }
+
+    /// Run until the program halts, needs input, produces output or hits one of the
+    /// `breakpoints`.
+    pub fn resume(&mut self) -> Result<StopReason, IntcodeError> {
+        if !self.breakpoints.is_empty() {
+            return self.run_until(|_| false);
+        }
+        loop {
+            if let Some(reason) = self.advance()? {
+                return Ok(reason);
+            }
+        }
+    }
+
+    // Like `step`, but may execute a whole block at once when using the threaded backend
+    fn advance(&mut self) -> Result<Option<StopReason>, IntcodeError> {
+        // Tracing and profiling want to see every instruction
+        if self.trace.is_some() || self.profile.is_some() {
+            return self.step();
+        }
+        let block = match &mut self.threaded {
+            Some(threaded) => threaded.block(&self.memory, self.pc, self.overflow),
+            None => None,
+        };
+        let block = match block {
+            Some(block) => block,
+            None => return self.step(),
+        };
+        for op in &block.ops {
+            // Keep the pc at the instruction while it runs, so errors point at it
+            self.pc = op.pc;
+            self.check_budget()?;
+            let effect = (op.run)(&mut self.memory, &mut self.relative_base)?;
+            self.steps += 1;
+            match effect {
+                Effect::Next => {}
+                Effect::Wrote(addr) => {
+                    if self.wrote(addr) {
+                        // The rest of this block may be stale
+                        self.pc = op.next;
+                        return Ok(None);
                     }
                 }
-                LessThan {
-                    a,
-                    b,
-                    result_location,
-                } => {
-                    let value = if self.get_param(a)? < self.get_param(b)? {
-                        1
-                    } else {
-                        0
-                    };
-                    self.set_param(result_location, value)?;
+                Effect::Jump(target) => {
+                    self.pc = target;
+                    return Ok(None);
                 }
-                Equals {
-                    a,
-                    b,
-                    result_location,
-                } => {
-                    let value = if self.get_param(a)? == self.get_param(b)? {
-                        1
-                    } else {
-                        0
-                    };
+            }
+        }
+        self.pc = block.end;
+        Ok(None)
+    }
+
+    /// Like `resume`, but also stops with `StopReason::Breakpoint` when `breakpoint` returns
+    /// true for the state before an instruction. The instruction at the current pc is always
+    /// executed, so calling this again continues past the breakpoint.
+    pub fn run_until<F>(&mut self, mut breakpoint: F) -> Result<StopReason, IntcodeError>
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        loop {
+            if let Some(reason) = self.step()? {
+                return Ok(reason);
+            }
+            if breakpoint(self) {
+                return Ok(StopReason::Breakpoint);
+            }
+            if let Some(reason) = self.check_breakpoints() {
+                return Ok(reason);
+            }
+        }
+    }
+
+    fn check_breakpoints(&self) -> Option<StopReason> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        if self
+            .breakpoints
+            .breaks_at(self.pc, self.relative_base, &self.memory)
+        {
+            return Some(StopReason::Breakpoint);
+        }
+        // If the instruction can't be decoded, let step report the error
+        let instr = self.decode(self.pc).ok()?;
+        let (reads, write) = instr.operands();
+        let reads = reads.into_iter().map(|param| (param, Access::Read));
+        let write = write.into_iter().map(|param| (param, Access::Write));
+        reads
+            .chain(write)
+            .filter_map(|(param, access)| Some((self.address(param)?, access)))
+            .find(|&(address, access)| self.breakpoints.is_watched(address, access))
+            .map(|(address, access)| StopReason::Watchpoint { address, access })
+    }
+
+    // The memory address a parameter refers to, if any
+    fn address(&self, param: Param) -> Option<usize> {
+        match param {
+            Param::Pos(pos) => Some(pos),
+            Param::Immediate(_) => None,
+            Param::Relative(rel) => Some((self.relative_base + rel) as usize),
+        }
+    }
+
+    /// Execute a single instruction. Returns `None` if the program can just keep going.
+    ///
+    /// When stopping on `Halted` or `NeedsInput` the pc is left at the instruction, so
+    /// stepping again after queueing input retries it.
+    pub fn step(&mut self) -> Result<Option<StopReason>, IntcodeError> {
+        self.check_budget()?;
+        let traced = match self.trace {
+            Some(_) => Some(self.begin_trace_event()?),
+            None => None,
+        };
+        let profiled = match self.profile {
+            Some(_) => Some((self.pc, self.opcode(), self.decode(self.pc)?)),
+            None => None,
+        };
+        let stop = self.execute()?;
+        if stop == Some(StopReason::NeedsInput) {
+            return Ok(stop);
+        }
+        if let Some((event, write)) = traced {
+            self.finish_trace_event(event, write, stop);
+        }
+        if let (Some(profile), Some((pc, opcode, instr))) = (&mut self.profile, profiled) {
+            profile.record(pc, opcode, &instr, self.pc);
+        }
+        self.steps += 1;
+        Ok(stop)
+    }
+
+    fn check_budget(&self) -> Result<(), IntcodeError> {
+        match self
+            .budget
+            .as_ref()
+            .and_then(|meter| meter.check(self.steps))
+        {
+            Some(limit) => Err(IntcodeError::BudgetExceeded { pc: self.pc, limit }),
+            None => Ok(()),
+        }
+    }
+
+    // Record what the instruction at the pc is about to do. Returns the event along with
+    // the address it will write to, if any.
+    fn begin_trace_event(&self) -> Result<(TraceEvent, Option<usize>), IntcodeError> {
+        let instr = self.decode(self.pc)?;
+        let (reads, write) = instr.operands();
+        let operands = reads
+            .into_iter()
+            .map(|param| self.get_param(param))
+            .collect::<Result<_, _>>()?;
+        let event = TraceEvent {
+            step: self.steps,
+            pc: self.pc,
+            base: self.relative_base,
+            opcode: self.opcode(),
+            instr: instr.to_string(),
+            operands,
+            writes: Vec::new(),
+            input: None,
+            output: None,
+        };
+        Ok((event, write.and_then(|param| self.address(param))))
+    }
+
+    fn finish_trace_event(
+        &mut self,
+        mut event: TraceEvent,
+        write: Option<usize>,
+        stop: Option<StopReason>,
+    ) {
+        if let Some(addr) = write {
+            let value = self.memory.get(addr).unwrap_or(0);
+            event.writes.push((addr, value));
+            if event.opcode % 100 == 3 {
+                event.input = Some(value);
+            }
+        }
+        if let Some(StopReason::ProducedOutput(value)) = stop {
+            event.output = Some(value);
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.events.push(event);
+        }
+    }
+
+    fn execute(&mut self) -> Result<Option<StopReason>, IntcodeError> {
+        let instr = self.fetch()?;
+        // The pc stays at the instruction while it executes, so errors can point at it
+        let mut next_pc = self.pc + instr.len();
+        use Instruction::*;
+        let stop = match instr {
+            Add {
+                a,
+                b,
+                result_location,
+            } => {
+                let value = self.overflow.add(self.get_param(a)?, self.get_param(b)?);
+                self.set_param(result_location, self.checked(value)?)?;
+                None
+            }
+            Multiply {
+                a,
+                b,
+                result_location,
+            } => {
+                let value = self.overflow.mul(self.get_param(a)?, self.get_param(b)?);
+                self.set_param(result_location, self.checked(value)?)?;
+                None
+            }
+            Input { result_location } => match self.input.pop_front() {
+                Some(value) => {
                     self.set_param(result_location, value)?;
+                    None
+                }
+                None => return Ok(Some(StopReason::NeedsInput)),
+            },
+            Output { param } => {
+                let value = self.get_param(param)?;
+                if let Some(limit) = self.budget.as_mut().and_then(Meter::output) {
+                    return Err(IntcodeError::BudgetExceeded { pc: self.pc, limit });
+                }
+                Some(StopReason::ProducedOutput(value))
+            }
+            JumpIfTrue { check, jump_to } => {
+                if self.get_param(check)? > 0 {
+                    next_pc = self.get_param(jump_to)? as usize;
                 }
-                AdjustRelativeBase { value } => {
-                    let value = self.get_param(value)?;
-                    self.relative_base += value;
+                None
+            }
+            JumpIfFalse { check, jump_to } => {
+                if self.get_param(check)? == 0 {
+                    next_pc = self.get_param(jump_to)? as usize;
                 }
-                Halt => return Ok(()),
-                // unknown => unimplemented!("{:?}", unknown),
+                None
             }
+            LessThan {
+                a,
+                b,
+                result_location,
+            } => {
+                let value = if self.get_param(a)? < self.get_param(b)? {
+                    1
+                } else {
+                    0
+                };
+                self.set_param(result_location, value)?;
+                None
+            }
+            Equals {
+                a,
+                b,
+                result_location,
+            } => {
+                let value = if self.get_param(a)? == self.get_param(b)? {
+                    1
+                } else {
+                    0
+                };
+                self.set_param(result_location, value)?;
+                None
+            }
+            AdjustRelativeBase { value } => {
+                let value = self.get_param(value)?;
+                self.relative_base += value;
+                None
+            }
+            Halt => return Ok(Some(StopReason::Halted)),
+        };
+        self.pc = next_pc;
+        Ok(stop)
+    }
+
+    // The result of an arithmetic instruction, `Err` with the exact result if it overflowed
+    fn checked(&self, value: Result<i64, i128>) -> Result<i64, IntcodeError> {
+        value.map_err(|value| self.overflow.error(self.pc, self.opcode(), value))
+    }
+
+    // Decode the instruction at the pc, going through the cache
+    fn fetch(&mut self) -> Result<Instruction, IntcodeError> {
+        if let Some(instr) = self.cache.as_ref().and_then(|cache| cache.get(self.pc)) {
+            return Ok(instr);
+        }
+        let instr = self.decode(self.pc)?;
+        if let Some(cache) = &mut self.cache {
+            cache.insert(self.pc, instr);
         }
+        Ok(instr)
     }
 
-    fn next_i64(&mut self) -> Result<i64> {
+    fn next_i64(&mut self) -> Result<i64, IntcodeError> {
         let val = self.get(self.pc)?;
         self.pc += 1;
         Ok(val)
     }
 
-    fn next_instr(&mut self) -> Result<Instruction> {
-        let mut full_op = self.next_i64()?;
-        let op = full_op % 100;
-        full_op /= 100;
-        let mut modes = Vec::new();
-        while full_op > 0 {
-            modes.push(full_op % 10);
-            full_op /= 10;
-        }
-        Ok(match op {
-            1 => Instruction::Add {
-                a: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-                b: Param::from_mode(modes.get(1).copied().unwrap_or(0), self.next_i64()?)?,
-                result_location: Param::from_mode(
-                    modes.get(2).copied().unwrap_or(0),
-                    self.next_i64()?,
-                )?,
-            },
-            2 => Instruction::Multiply {
-                a: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-                b: Param::from_mode(modes.get(1).copied().unwrap_or(0), self.next_i64()?)?,
-                result_location: Param::from_mode(
-                    modes.get(2).copied().unwrap_or(0),
-                    self.next_i64()?,
-                )?,
-            },
-            3 => Instruction::Input {
-                result_location: Param::from_mode(
-                    modes.get(0).copied().unwrap_or(0),
-                    self.next_i64()?,
-                )?,
-            },
-            4 => Instruction::Output {
-                param: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-            },
-            5 => Instruction::JumpIfTrue {
-                check: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-                jump_to: Param::from_mode(modes.get(1).copied().unwrap_or(0), self.next_i64()?)?,
-            },
-            6 => Instruction::JumpIfFalse {
-                check: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-                jump_to: Param::from_mode(modes.get(1).copied().unwrap_or(0), self.next_i64()?)?,
-            },
-            7 => Instruction::LessThan {
-                a: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-                b: Param::from_mode(modes.get(1).copied().unwrap_or(0), self.next_i64()?)?,
-                result_location: Param::from_mode(
-                    modes.get(2).copied().unwrap_or(0),
-                    self.next_i64()?,
-                )?,
-            },
-            8 => Instruction::Equals {
-                a: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-                b: Param::from_mode(modes.get(1).copied().unwrap_or(0), self.next_i64()?)?,
-                result_location: Param::from_mode(
-                    modes.get(2).copied().unwrap_or(0),
-                    self.next_i64()?,
-                )?,
-            },
-            9 => Instruction::AdjustRelativeBase {
-                value: Param::from_mode(modes.get(0).copied().unwrap_or(0), self.next_i64()?)?,
-            },
-            99 => Instruction::Halt,
-            unknown => Err(anyhow!("Unknown instruction {}", unknown))?,
+    fn next_instr(&mut self) -> Result<Instruction, IntcodeError> {
+        let pc = self.pc;
+        Instruction::decode_with(pc, || self.next_i64())
+    }
+
+    /// Decode the instruction at `pc` without executing it.
+    pub fn decode(&self, pc: usize) -> Result<Instruction, IntcodeError> {
+        let mut addr = pc;
+        Instruction::decode_with(pc, || {
+            let value = self.memory.get(addr).ok_or_else(|| {
+                if addr == pc {
+                    IntcodeError::PcOutOfRange { pc }
+                } else {
+                    IntcodeError::InvalidRead {
+                        pc,
+                        opcode: self.memory.get(pc).unwrap_or(0),
+                        address: addr as i64,
+                    }
+                }
+            })?;
+            addr += 1;
+            Ok(value)
         })
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
-enum Param {
+pub enum Param {
     Pos(usize),
     Immediate(i64),
     Relative(i64),
 }
 
 impl Param {
-    fn from_mode(mode: i64, value: i64) -> Result<Param> {
+    fn from_mode(pc: usize, opcode: i64, mode: i64, value: i64) -> Result<Param, IntcodeError> {
         Ok(match mode {
             0 => Param::Pos(value as usize),
             1 => Param::Immediate(value),
             2 => Param::Relative(value),
-            mode => Err(anyhow!("Unknown mode {}", mode))?,
+            mode => return Err(IntcodeError::UnknownMode { pc, opcode, mode }),
         })
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum Instruction {
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Instruction {
     Add {
         a: Param,
         b: Param,
@@ -360,40 +900,219 @@ enum Instruction {
     Halt,
 }
 
-impl Display for Instruction {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl Instruction {
+    /// Decode the instruction at `pc` in `program`.
+    pub fn decode<T>(program: &[T], pc: usize) -> Result<Instruction, IntcodeError>
+    where
+        T: Copy + Into<i64>,
+    {
+        let mut addr = pc;
+        Instruction::decode_with(pc, || {
+            let value = program.get(addr).map(|&word| word.into()).ok_or_else(|| {
+                if addr == pc {
+                    IntcodeError::PcOutOfRange { pc }
+                } else {
+                    IntcodeError::InvalidRead {
+                        pc,
+                        opcode: program[pc].into(),
+                        address: addr as i64,
+                    }
+                }
+            })?;
+            addr += 1;
+            Ok(value)
+        })
+    }
+
+    /// Decode the instruction at `pc`. `next` yields the opcode and then each parameter.
+    fn decode_with<F>(pc: usize, mut next: F) -> Result<Instruction, IntcodeError>
+    where
+        F: FnMut() -> Result<i64, IntcodeError>,
+    {
+        let opcode = next()?;
+        let op = opcode % 100;
+        let mut modes = opcode / 100;
+        // Parameters are decoded in order, so each one takes the next mode digit
+        let mut param = || -> Result<Param, IntcodeError> {
+            let mode = modes % 10;
+            modes /= 10;
+            Param::from_mode(pc, opcode, mode, next()?)
+        };
+        Ok(match op {
+            1 => Instruction::Add {
+                a: param()?,
+                b: param()?,
+                result_location: param()?,
+            },
+            2 => Instruction::Multiply {
+                a: param()?,
+                b: param()?,
+                result_location: param()?,
+            },
+            3 => Instruction::Input {
+                result_location: param()?,
+            },
+            4 => Instruction::Output { param: param()? },
+            5 => Instruction::JumpIfTrue {
+                check: param()?,
+                jump_to: param()?,
+            },
+            6 => Instruction::JumpIfFalse {
+                check: param()?,
+                jump_to: param()?,
+            },
+            7 => Instruction::LessThan {
+                a: param()?,
+                b: param()?,
+                result_location: param()?,
+            },
+            8 => Instruction::Equals {
+                a: param()?,
+                b: param()?,
+                result_location: param()?,
+            },
+            9 => Instruction::AdjustRelativeBase { value: param()? },
+            99 => Instruction::Halt,
+            _ => return Err(IntcodeError::UnknownInstruction { pc, opcode }),
+        })
+    }
+
+    /// The parameters the instruction reads, and the one it writes to, if any.
+    pub fn operands(&self) -> (Vec<Param>, Option<Param>) {
+        use Instruction::*;
+        match *self {
+            Add {
+                a,
+                b,
+                result_location,
+            }
+            | Multiply {
+                a,
+                b,
+                result_location,
+            }
+            | LessThan {
+                a,
+                b,
+                result_location,
+            }
+            | Equals {
+                a,
+                b,
+                result_location,
+            } => (vec![a, b], Some(result_location)),
+            Input { result_location } => (vec![], Some(result_location)),
+            Output { param } => (vec![param], None),
+            JumpIfTrue { check, jump_to } | JumpIfFalse { check, jump_to } => {
+                (vec![check, jump_to], None)
+            }
+            AdjustRelativeBase { value } => (vec![value], None),
+            Halt => (vec![], None),
+        }
+    }
+
+    /// Number of words the instruction takes up, including the opcode.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
         use Instruction::*;
+        match self {
+            Add { .. } | Multiply { .. } | LessThan { .. } | Equals { .. } => 4,
+            JumpIfTrue { .. } | JumpIfFalse { .. } => 3,
+            Input { .. } | Output { .. } | AdjustRelativeBase { .. } => 2,
+            Halt => 1,
+        }
+    }
+}
+
+impl Instruction {
+    /// Format the instruction like `Display` does, but let `param` write each parameter.
+    /// `param` gets the index of the parameter along with it, e.g. 1 for a jump target.
+    pub fn fmt_with<P>(&self, f: &mut fmt::Formatter, param: P) -> fmt::Result
+    where
+        P: Fn(usize, &Param) -> String,
+    {
+        use Instruction::*;
+        // ADD, MUL and INPUT put an extra & in front of where they write, like `=> &&3`
+        let written = match self {
+            Add { .. } | Multiply { .. } => Some(2),
+            Input { .. } => Some(0),
+            _ => None,
+        };
+        let param = |index, value: &Param| {
+            if Some(index) == written {
+                format!("&{}", param(index, value))
+            } else {
+                param(index, value)
+            }
+        };
         match self {
             Add {
                 a,
                 b,
                 result_location,
-            } => write!(f, "ADD {} {} => &{}", a, b, result_location),
+            } => write!(
+                f,
+                "ADD {} {} => {}",
+                param(0, a),
+                param(1, b),
+                param(2, result_location)
+            ),
             Multiply {
                 a,
                 b,
                 result_location,
-            } => write!(f, "MUL {} {} => &{}", a, b, result_location),
-            Input { result_location } => write!(f, "INPUT &{}", result_location),
-            Output { param } => write!(f, "OUTPUT {}", param),
-            JumpIfTrue { check, jump_to } => write!(f, "IF {} JUMP TO {}", check, jump_to),
-            JumpIfFalse { check, jump_to } => write!(f, "IF NOT {} JUMP TO {}", check, jump_to),
+            } => write!(
+                f,
+                "MUL {} {} => {}",
+                param(0, a),
+                param(1, b),
+                param(2, result_location)
+            ),
+            Input { result_location } => write!(f, "INPUT {}", param(0, result_location)),
+            Output { param: value } => write!(f, "OUTPUT {}", param(0, value)),
+            JumpIfTrue { check, jump_to } => {
+                write!(f, "IF {} JUMP TO {}", param(0, check), param(1, jump_to))
+            }
+            JumpIfFalse { check, jump_to } => write!(
+                f,
+                "IF NOT {} JUMP TO {}",
+                param(0, check),
+                param(1, jump_to)
+            ),
             LessThan {
                 a,
                 b,
                 result_location,
-            } => write!(f, "IF {} < {} => {}", a, b, result_location),
+            } => write!(
+                f,
+                "IF {} < {} => {}",
+                param(0, a),
+                param(1, b),
+                param(2, result_location)
+            ),
             Equals {
                 a,
                 b,
                 result_location,
-            } => write!(f, "IF {} == {} => {}", a, b, result_location),
-            AdjustRelativeBase { value } => write!(f, "REL_BASE += {}", value),
+            } => write!(
+                f,
+                "IF {} == {} => {}",
+                param(0, a),
+                param(1, b),
+                param(2, result_location)
+            ),
+            AdjustRelativeBase { value } => write!(f, "REL_BASE += {}", param(0, value)),
             Halt => write!(f, "HALT"),
         }
     }
 }
 
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with(f, |_, param| param.to_string())
+    }
+}
+
 impl Display for Param {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Param::*;
@@ -408,6 +1127,9 @@ impl Display for Param {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::budget::Limit;
+    use crate::overflow::Overflow;
+    use std::time::Duration;
 
     fn input_output(program: &[i64], input: i64) -> Result<i64> {
         task::block_on(async {
@@ -433,7 +1155,7 @@ mod tests {
         task::block_on(async {
             let mut computer = Computer::from_mem(program);
             let output = computer.create_output_channel();
-            let computer_task = computer.spawn().await;
+            let computer_task = computer.spawn();
             let mut result = Vec::new();
             while let Some(value) = output.recv().await {
                 result.push(value);
@@ -486,6 +1208,91 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn memory_grows_on_demand() -> Result<()> {
+        // Write far past the program using the relative base, then read it back
+        assert_eq!(input_output(&[109, 100_000, 203, 0, 204, 0, 99], 42)?, 42);
+        assert_eq!(get_output(vec![4, 1_000_000, 99])?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn memory_limit() -> Result<()> {
+        let mut computer = Computer::from_mem(vec![4, 1_000_000, 99]);
+        computer.set_memory_limit(0x8000);
+        match computer.step() {
+            Err(IntcodeError::InvalidRead {
+                pc: 0,
+                opcode: 4,
+                address: 1_000_000,
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        // Lowering the limit below what is in use drops the rest
+        computer.set_memory_limit(2);
+        assert_eq!(computer.snapshot().memory, vec![4, 1_000_000]);
+
+        let mut computer = Computer::from_mem_noresize(vec![1101, 1, 1, 5, 99]);
+        match computer.step() {
+            Err(IntcodeError::InvalidWrite {
+                pc: 0,
+                opcode: 1101,
+                address: 5,
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn errors_point_at_instruction() {
+        let mut computer = Computer::from_mem(vec![1101, 1, 1, 0, 42]);
+        computer.step().unwrap();
+        match computer.step() {
+            Err(IntcodeError::UnknownInstruction { pc: 4, opcode: 42 }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let mut computer = Computer::from_mem(vec![104, 1, 301, 0, 0, 0]);
+        computer.step().unwrap();
+        match computer.step() {
+            Err(IntcodeError::UnknownMode {
+                pc: 2,
+                opcode: 301,
+                mode: 3,
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let mut computer = Computer::from_mem(vec![11101, 1, 1, 0, 99]);
+        match computer.step() {
+            Err(IntcodeError::WriteToImmediate {
+                pc: 0,
+                opcode: 11101,
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let mut computer = Computer::from_mem(vec![1105, 1, -1]);
+        computer.step().unwrap();
+        match computer.step() {
+            Err(IntcodeError::PcOutOfRange { .. }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[async_std::test]
+    async fn input_closed() {
+        let mut computer = Computer::from_mem(vec![3, 0, 3, 0, 99]);
+        let input = computer.create_input_channel();
+        input.send(1).await;
+        drop(input);
+        match computer.run().await {
+            Err(IntcodeError::InputClosed { pc: 2 }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
     #[test]
     fn test_get_param() -> Result<()> {
         let computer = Computer::from_mem(vec![9, 42, 7]);
@@ -512,6 +1319,128 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn run_with_inputs() -> Result<()> {
+        // Output the sum of two inputs, twice
+        let program = vec![3, 20, 3, 21, 1, 20, 21, 22, 4, 22, 4, 22, 99];
+        let mut computer = Computer::from_mem(program.clone());
+        assert_eq!(computer.run_with_inputs(&[2, 3])?, vec![5, 5]);
+
+        let mut computer = Computer::from_mem(program);
+        match computer.run_with_inputs(&[2]) {
+            Err(IntcodeError::InputClosed { pc: 2 }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn queue_io() -> Result<()> {
+        let mut computer =
+            Computer::from_mem(vec![3, 0, 4, 0, 4, 0, 99]).with_io(QueueIO::new(&[7]));
+        computer.run().await?;
+        assert_eq!(computer.into_io().output, vec![7, 7]);
+        Ok(())
+    }
+
+    #[test]
+    fn step_budget() -> Result<()> {
+        // Count up forever
+        let program = vec![1001, 7, 1, 7, 1105, 1, 0, 0];
+        for &backend in &[Backend::Interpreter, Backend::Threaded] {
+            let mut computer = Computer::from_mem(program.clone());
+            computer.set_backend(backend);
+            computer.set_budget(Budget::new().steps(5));
+            match computer.resume() {
+                Err(IntcodeError::BudgetExceeded {
+                    pc: 4,
+                    limit: Limit::Steps(5),
+                }) => {}
+                other => panic!("Unexpected result {:?}", other),
+            }
+            assert_eq!(computer.steps(), 5);
+            assert_eq!(computer.memory().get(7), Some(3));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_policy() {
+        // Double a number while it is positive, then output it
+        let program = vec![1002, 10, 2, 10, 1005, 10, 0, 4, 10, 99, 1 << 60];
+        for &backend in &[Backend::Interpreter, Backend::Threaded] {
+            let computer = |overflow| {
+                let mut computer = Computer::from_mem(program.clone());
+                computer.set_backend(backend);
+                computer.set_overflow(overflow);
+                computer.set_budget(Budget::new().steps(100));
+                computer
+            };
+
+            let mut wrapping = computer(Overflow::Wrapping);
+            assert_eq!(wrapping.run_with_inputs(&[]).unwrap(), vec![i64::MIN]);
+
+            // Stuck at the maximum
+            let mut saturating = computer(Overflow::Saturating);
+            match saturating.run_with_inputs(&[]) {
+                Err(IntcodeError::BudgetExceeded { .. }) => {}
+                other => panic!("Unexpected result {:?}", other),
+            }
+            assert_eq!(saturating.memory().get(10), Some(i64::MAX));
+
+            let mut checked = computer(Overflow::Checked);
+            match checked.run_with_inputs(&[]) {
+                Err(IntcodeError::ArithmeticOverflow {
+                    pc: 0,
+                    opcode: 1002,
+                }) => {}
+                other => panic!("Unexpected result {:?}", other),
+            }
+            assert_eq!(checked.memory().get(10), Some(1 << 62));
+
+            // Fails with the exact result
+            let mut wide = computer(Overflow::Wide);
+            match wide.run_with_inputs(&[]) {
+                Err(IntcodeError::WordOverflow {
+                    pc: 0,
+                    opcode: 1002,
+                    value,
+                }) => assert_eq!(value, 1 << 63),
+                other => panic!("Unexpected result {:?}", other),
+            }
+            assert_eq!(wide.memory().get(10), Some(1 << 62));
+        }
+    }
+
+    #[async_std::test]
+    async fn output_budget() {
+        // Output 1 forever
+        let mut computer = Computer::from_mem(vec![104, 1, 1105, 1, 0]).with_io(QueueIO::new(&[]));
+        computer.set_budget(Budget::new().output(3));
+        match computer.run().await {
+            Err(IntcodeError::BudgetExceeded {
+                pc: 0,
+                limit: Limit::Output(3),
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert_eq!(computer.into_io().output, vec![1, 1, 1]);
+    }
+
+    #[async_std::test]
+    async fn time_budget() {
+        let mut computer = Computer::from_mem(vec![1105, 1, 0]);
+        let time = Duration::from_millis(20);
+        computer.set_budget(Budget::new().time(time));
+        match computer.spawn().await {
+            Err(IntcodeError::BudgetExceeded {
+                pc: 0,
+                limit: Limit::Time(limit),
+            }) => assert_eq!(limit, time),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
     #[test]
     fn test_next_i64() -> Result<()> {
         let mut program = Computer::from_mem(vec![1, 0, 2]);
@@ -645,7 +1574,7 @@ mod tests {
         let mut computer = Computer::from_mem(vec![3, 0, 3, 4, 99, 4, 0, 0, 4, 0, 4, 4, 99]);
         let output = computer.create_output_channel();
         let input = computer.create_input_channel();
-        let handle = computer.spawn().await;
+        let handle = computer.spawn();
         input.send(23).await;
         input.send(2).await;
         // Signal that we wont't send any more input. Means that the computer should not
@@ -657,10 +1586,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn step_through_program() -> Result<()> {
+        let mut computer = Computer::from_mem(vec![3, 0, 4, 0, 99]);
+        assert_eq!(computer.step()?, Some(StopReason::NeedsInput));
+        assert_eq!(computer.pc(), 0);
+        computer.push_input(7);
+        assert_eq!(computer.step()?, None);
+        assert_eq!(computer.step()?, Some(StopReason::ProducedOutput(7)));
+        assert_eq!(computer.step()?, Some(StopReason::Halted));
+        assert_eq!(computer.step()?, Some(StopReason::Halted));
+        assert_eq!(computer.pc(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn resume_until_halted() -> Result<()> {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut computer = Computer::from_mem(program.clone());
+        let mut output = Vec::new();
+        while let StopReason::ProducedOutput(value) = computer.resume()? {
+            output.push(value);
+        }
+        assert_eq!(output, program);
+        Ok(())
+    }
+
+    #[test]
+    fn run_until_breakpoint() -> Result<()> {
+        let mut computer = Computer::from_mem(vec![1101, 1, 1, 0, 1101, 2, 2, 0, 99]);
+        assert_eq!(computer.run_until(|c| c.pc() == 4)?, StopReason::Breakpoint);
+        assert_eq!(computer.get(0)?, 2);
+        assert_eq!(computer.run_until(|c| c.pc() == 4)?, StopReason::Halted);
+        assert_eq!(computer.get(0)?, 4);
+        Ok(())
+    }
+
     fn finish(program: Vec<i64>) -> Result<Vec<i64>> {
         let mut computer = Computer::from_mem_noresize(program);
         task::block_on(computer.run())?;
-        Ok(computer.memory)
+        Ok(computer.memory.to_vec())
     }
 
     #[test]
@@ -0,0 +1,221 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+use crate::computer::{Instruction, Param};
+
+/// Words of data per `DATA` line in the listing
+const DATA_PER_LINE: usize = 8;
+
+/// A disassembled program. Code is found by following the control flow from address 0, so
+/// data embedded in the program is not decoded as instructions.
+#[derive(Debug, Clone)]
+pub struct Disassembly {
+    program: Vec<i64>,
+    /// The instructions that are reachable, by address.
+    pub code: BTreeMap<usize, Instruction>,
+    /// Addresses that are jumped to (or returned to), and get a label in the listing.
+    pub labels: BTreeSet<usize>,
+    /// Comments to print after the instruction at an address.
+    pub comments: BTreeMap<usize, String>,
+}
+
+pub fn disassemble(program: &[i64]) -> Disassembly {
+    let mut code = BTreeMap::new();
+    let mut labels = BTreeSet::new();
+    let mut todo = vec![0];
+    // Every address covered by a decoded instruction, so we never decode overlapping code
+    let mut covered = BTreeSet::new();
+
+    while let Some(mut pc) = todo.pop() {
+        let mut return_address = None;
+        while !covered.contains(&pc) {
+            let instr = match Instruction::decode(program, pc) {
+                Ok(instr) => instr,
+                Err(_) => break,
+            };
+            if (pc..pc + instr.len()).any(|addr| covered.contains(&addr)) {
+                break;
+            }
+            covered.extend(pc..pc + instr.len());
+            let next = pc + instr.len();
+            let (target, falls_through) = match &instr {
+                Instruction::Halt => (None, false),
+                Instruction::JumpIfTrue { jump_to, .. }
+                | Instruction::JumpIfFalse { jump_to, .. } => {
+                    let target = match jump_to {
+                        Param::Immediate(target) if *target >= 0 => Some(*target as usize),
+                        _ => None,
+                    };
+                    (target, !is_unconditional(&instr))
+                }
+                _ => (None, true),
+            };
+            if let Some(target) = target {
+                labels.insert(target);
+                todo.push(target);
+                // Calls push their return address right before jumping, so the code after the
+                // call is only reachable through that constant.
+                if !falls_through {
+                    if let Some(address) = return_address {
+                        labels.insert(address);
+                        todo.push(address);
+                    }
+                }
+            }
+            return_address = constant(&instr)
+                .filter(|&value| value >= 0)
+                .map(|v| v as usize);
+            code.insert(pc, instr);
+            if !falls_through {
+                break;
+            }
+            pc = next;
+        }
+    }
+
+    labels.retain(|addr| code.contains_key(addr));
+
+    Disassembly {
+        program: program.to_vec(),
+        code,
+        labels,
+        comments: BTreeMap::new(),
+    }
+}
+
+/// A jump that is always taken, because its check is an immediate.
+pub fn is_unconditional(instr: &Instruction) -> bool {
+    match instr {
+        Instruction::JumpIfTrue {
+            check: Param::Immediate(value),
+            ..
+        } => *value > 0,
+        Instruction::JumpIfFalse {
+            check: Param::Immediate(value),
+            ..
+        } => *value == 0,
+        _ => false,
+    }
+}
+
+/// The value an instruction stores if it just moves a constant, like `ADD 42 0 => &&7`.
+pub fn constant(instr: &Instruction) -> Option<i64> {
+    use Param::Immediate;
+    match instr {
+        Instruction::Add {
+            a: Immediate(a),
+            b: Immediate(b),
+            ..
+        } if *a == 0 || *b == 0 => Some(a + b),
+        Instruction::Multiply {
+            a: Immediate(a),
+            b: Immediate(b),
+            ..
+        } if *a == 1 || *b == 1 => Some(a * b),
+        _ => None,
+    }
+}
+
+pub fn label(addr: usize) -> String {
+    format!("L{}", addr)
+}
+
+impl Disassembly {
+    /// The index of the parameter of `instr` that should be written as a label, if any.
+    fn labelled_param(&self, instr: &Instruction) -> Option<usize> {
+        let is_label = |param: &Param| match param {
+            Param::Immediate(value) if *value >= 0 => self.labels.contains(&(*value as usize)),
+            _ => false,
+        };
+        match instr {
+            Instruction::JumpIfTrue { jump_to, .. } | Instruction::JumpIfFalse { jump_to, .. }
+                if is_label(jump_to) =>
+            {
+                Some(1)
+            }
+            Instruction::Add { a, .. } | Instruction::Multiply { a, .. } => {
+                match constant(instr).map(Param::Immediate) {
+                    Some(value) if is_label(&value) => Some(if value == *a { 0 } else { 1 }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn fmt_data(&self, f: &mut fmt::Formatter, start: usize, end: usize) -> fmt::Result {
+        let mut addr = start;
+        while addr < end {
+            let line_end = end.min(addr + DATA_PER_LINE);
+            let values: Vec<String> = self.program[addr..line_end]
+                .iter()
+                .map(|value| value.to_string())
+                .collect();
+            writeln!(f, "{:>6}: DATA {}", addr, values.join(", "))?;
+            addr = line_end;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut addr = 0;
+        for (&pc, instr) in &self.code {
+            self.fmt_data(f, addr, pc)?;
+            if self.labels.contains(&pc) {
+                writeln!(f, "{}:", label(pc))?;
+            }
+            write!(f, "{:>6}: ", pc)?;
+            let labelled = self.labelled_param(instr);
+            instr.fmt_with(f, |index, param| match param {
+                Param::Immediate(value) if Some(index) == labelled => label(*value as usize),
+                param => param.to_string(),
+            })?;
+            if let Some(comment) = self.comments.get(&pc) {
+                write!(f, "  # {}", comment)?;
+            }
+            writeln!(f)?;
+            addr = pc + instr.len();
+        }
+        self.fmt_data(f, addr, self.program.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_code_and_data() {
+        let disasm = disassemble(&[1105, 1, 4, 42, 1101, 1, 2, 3, 99, 7, 8]);
+        assert_eq!(
+            disasm.code.keys().copied().collect::<Vec<_>>(),
+            vec![0, 4, 8]
+        );
+        assert_eq!(
+            disasm.to_string(),
+            "     0: IF 1 JUMP TO L4
+     3: DATA 42
+L4:
+     4: ADD 1 2 => &&3
+     8: HALT
+     9: DATA 7, 8
+"
+        );
+    }
+
+    #[test]
+    fn follows_calls() {
+        // 0: push return address 9, jump to 12; 9: halt; 12: return
+        let program = [21101, 9, 0, 0, 1105, 1, 12, 0, 0, 99, 0, 0, 2106, 0, 0];
+        let disasm = disassemble(&program);
+        assert_eq!(
+            disasm.code.keys().copied().collect::<Vec<_>>(),
+            vec![0, 4, 9, 12]
+        );
+        assert!(disasm.labels.contains(&9));
+        assert!(disasm.labels.contains(&12));
+        assert!(disasm.to_string().contains("ADD L9 0 => &base + 0"));
+    }
+}
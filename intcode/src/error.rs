@@ -0,0 +1,187 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::budget::Limit;
+
+/// Everything that can go wrong while running an intcode program. `pc` is always the address
+/// of the instruction that failed, and `opcode` its raw value (including parameter modes).
+#[derive(Debug)]
+pub enum IntcodeError {
+    UnknownInstruction {
+        pc: usize,
+        opcode: i64,
+    },
+    UnknownMode {
+        pc: usize,
+        opcode: i64,
+        mode: i64,
+    },
+    WriteToImmediate {
+        pc: usize,
+        opcode: i64,
+    },
+    InvalidRead {
+        pc: usize,
+        opcode: i64,
+        address: i64,
+    },
+    InvalidWrite {
+        pc: usize,
+        opcode: i64,
+        address: i64,
+    },
+    /// The program jumped to (or ran off into) an address outside memory.
+    PcOutOfRange {
+        pc: usize,
+    },
+    /// The program asked for input, but the IO reported that no more input will come.
+    InputClosed {
+        pc: usize,
+    },
+    /// Any other error from the `IO` implementation.
+    Io {
+        pc: usize,
+        source: anyhow::Error,
+    },
+    /// ADD or MULTIPLY overflowed, with `Overflow::Checked`.
+    ArithmeticOverflow {
+        pc: usize,
+        opcode: i64,
+    },
+    /// The result of ADD or MULTIPLY doesn't fit in a memory word, with `Overflow::Wide`.
+    WordOverflow {
+        pc: usize,
+        opcode: i64,
+        value: i128,
+    },
+    /// The program used up its `Budget`. `pc` is the instruction that would have gone over.
+    BudgetExceeded {
+        pc: usize,
+        limit: Limit,
+    },
+}
+
+impl IntcodeError {
+    /// Wrap an error returned from an `IO` implementation.
+    pub fn from_io(pc: usize, source: anyhow::Error) -> IntcodeError {
+        if source.downcast_ref::<EndOfInput>().is_some() {
+            IntcodeError::InputClosed { pc }
+        } else {
+            IntcodeError::Io { pc, source }
+        }
+    }
+
+    /// The address of the instruction that failed.
+    pub fn pc(&self) -> usize {
+        use IntcodeError::*;
+        match self {
+            UnknownInstruction { pc, .. }
+            | UnknownMode { pc, .. }
+            | WriteToImmediate { pc, .. }
+            | InvalidRead { pc, .. }
+            | InvalidWrite { pc, .. }
+            | PcOutOfRange { pc }
+            | InputClosed { pc }
+            | Io { pc, .. }
+            | ArithmeticOverflow { pc, .. }
+            | WordOverflow { pc, .. }
+            | BudgetExceeded { pc, .. } => *pc,
+        }
+    }
+}
+
+impl Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use IntcodeError::*;
+        match self {
+            UnknownInstruction { pc, opcode } => {
+                write!(f, "Unknown instruction {} at {}", opcode, pc)
+            }
+            UnknownMode { pc, opcode, mode } => write!(
+                f,
+                "Unknown mode {} in instruction {} at {}",
+                mode, opcode, pc
+            ),
+            WriteToImmediate { pc, opcode } => write!(
+                f,
+                "Cannot write to an immediate parameter, instruction {} at {}",
+                opcode, pc
+            ),
+            InvalidRead {
+                pc,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "Tried to read from invalid address {}, instruction {} at {}",
+                address, opcode, pc
+            ),
+            InvalidWrite {
+                pc,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "Tried to write to invalid address {}, instruction {} at {}",
+                address, opcode, pc
+            ),
+            PcOutOfRange { pc } => write!(f, "Program counter {} is outside memory", pc),
+            InputClosed { pc } => write!(f, "Input closed while waiting for input at {}", pc),
+            Io { pc, source } => write!(f, "IO error at {}: {}", pc, source),
+            ArithmeticOverflow { pc, opcode } => {
+                write!(f, "Arithmetic overflow in instruction {} at {}", opcode, pc)
+            }
+            WordOverflow { pc, opcode, value } => write!(
+                f,
+                "Cannot store {}, it doesn't fit in a word, instruction {} at {}",
+                value, opcode, pc
+            ),
+            BudgetExceeded { pc, limit } => {
+                write!(f, "Exceeded the budget of {} at {}", limit, pc)
+            }
+        }
+    }
+}
+
+impl Error for IntcodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IntcodeError::Io { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Error for `IO::input` implementations to return when no more input will ever arrive.
+/// The computer reports it as `IntcodeError::InputClosed`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct EndOfInput;
+
+impl Display for EndOfInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No more input")
+    }
+}
+
+impl Error for EndOfInput {}
+
+/// Every computer that hasn't halted is waiting for input that will never come.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Deadlock {
+    /// The stuck computers, as their index and the pc of the input instruction they are
+    /// waiting at.
+    pub blocked: Vec<(usize, usize)>,
+}
+
+impl Display for Deadlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let blocked: Vec<String> = self
+            .blocked
+            .iter()
+            .map(|(computer, pc)| format!("computer {} at {}", computer, pc))
+            .collect();
+        write!(f, "Deadlock, waiting for input: {}", blocked.join(", "))
+    }
+}
+
+impl Error for Deadlock {}
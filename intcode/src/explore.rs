@@ -0,0 +1,206 @@
+//! Searching over the states of a program, for puzzles like mazes and games where the
+//! answer is the right sequence of inputs.
+//!
+//! Starting from a computer, the explorer runs to the first input prompt, then forks a
+//! clone for each candidate input and runs each of them to their next prompt, breadth
+//! first. States are told apart by a key chosen by the caller, like a position read from
+//! memory or the output, and each key is only explored once. The first state the goal
+//! accepts is reached by the shortest sequence of inputs.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), intcode::IntcodeError> {
+//! use intcode::explore::StateExplorer;
+//! use intcode::Computer;
+//!
+//! # let program = vec![99];
+//! // Move north, south, west or east until the droid reports finding the oxygen system
+//! let mut explorer = StateExplorer::new(
+//!     &[1, 2, 3, 4],
+//!     |node| (node.computer.memory().get(1039), node.computer.memory().get(1040)),
+//!     |node| node.output == [2],
+//! );
+//! if let Some(node) = explorer.search(&Computer::from_mem(program))? {
+//!     println!("{} moves", node.inputs.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+use crate::computer::{Computer, StopReason};
+use crate::error::IntcodeError;
+
+/// A state of the program, waiting for input or halted.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub computer: Computer,
+    /// The inputs given to get here, in order.
+    pub inputs: Vec<i64>,
+    /// What the program output since it was last given input.
+    pub output: Vec<i64>,
+    pub halted: bool,
+}
+
+pub struct StateExplorer<K, FK, FG> {
+    inputs: Vec<i64>,
+    key: FK,
+    goal: FG,
+    max_depth: Option<usize>,
+    seen: HashSet<K>,
+    explored: usize,
+}
+
+impl<K, FK, FG> StateExplorer<K, FK, FG>
+where
+    K: Hash + Eq,
+    FK: FnMut(&Node) -> K,
+    FG: FnMut(&Node) -> bool,
+{
+    /// Explore by giving each of `inputs` at every prompt. `key` identifies a state for
+    /// deduplication, and `goal` says whether a state is what we are looking for.
+    pub fn new(inputs: &[i64], key: FK, goal: FG) -> Self {
+        StateExplorer {
+            inputs: inputs.to_vec(),
+            key,
+            goal,
+            max_depth: None,
+            seen: HashSet::new(),
+            explored: 0,
+        }
+    }
+
+    /// Give up on paths longer than `depth` inputs.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Number of states run by the last search.
+    pub fn explored(&self) -> usize {
+        self.explored
+    }
+
+    /// Search from `computer`, which is left as it is. Returns the first state reached that
+    /// satisfies the goal, with the shortest input sequence leading there in `inputs`, or
+    /// `None` if every state has been explored without finding it.
+    pub fn search(&mut self, computer: &Computer) -> Result<Option<Node>, IntcodeError> {
+        self.seen.clear();
+        self.explored = 0;
+        let mut queue = VecDeque::new();
+        let start = self.run(computer.clone(), Vec::new())?;
+        match self.visit(start) {
+            Visit::Goal(node) => return Ok(Some(node)),
+            Visit::Explore(node) => queue.push_back(node),
+            Visit::Skip => {}
+        }
+
+        while let Some(node) = queue.pop_front() {
+            if Some(node.inputs.len()) == self.max_depth {
+                continue;
+            }
+            for &input in &self.inputs.clone() {
+                let mut computer = node.computer.clone();
+                computer.push_input(input);
+                let mut inputs = node.inputs.clone();
+                inputs.push(input);
+                let next = self.run(computer, inputs)?;
+                match self.visit(next) {
+                    Visit::Goal(node) => return Ok(Some(node)),
+                    Visit::Explore(node) => queue.push_back(node),
+                    Visit::Skip => {}
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn run(&mut self, mut computer: Computer, inputs: Vec<i64>) -> Result<Node, IntcodeError> {
+        self.explored += 1;
+        let (output, reason) = computer.run_to_input()?;
+        Ok(Node {
+            computer,
+            inputs,
+            output,
+            halted: reason == StopReason::Halted,
+        })
+    }
+
+    fn visit(&mut self, node: Node) -> Visit {
+        if (self.goal)(&node) {
+            return Visit::Goal(node);
+        }
+        if node.halted || !self.seen.insert((self.key)(&node)) {
+            return Visit::Skip;
+        }
+        Visit::Explore(node)
+    }
+}
+
+enum Visit {
+    Goal(Node),
+    Explore(Node),
+    Skip,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Start at 0, input 1 adds one and anything else doubles, output the new value after
+    // each input and halt after passing 20
+    fn counter() -> Computer {
+        let program = crate::asm::assemble(
+            "
+            loop:
+                INPUT &in
+                IF &in == 1 => &inc
+                IF &inc JUMP TO add
+                ADD &x &x => &x
+                IF 1 JUMP TO show
+            add:
+                ADD &x 1 => &x
+            show:
+                OUTPUT &x
+                IF 20 < &x => &over
+                IF NOT &over JUMP TO loop
+                HALT
+            in: DATA 0
+            inc: DATA 0
+            over: DATA 0
+            x: DATA 0
+            ",
+        )
+        .unwrap();
+        Computer::from_mem(program)
+    }
+
+    #[test]
+    fn shortest_inputs() -> Result<(), IntcodeError> {
+        let mut explorer = StateExplorer::new(
+            &[1, 2],
+            |node| node.output.clone(),
+            |node| node.output == [10],
+        );
+        let node = explorer.search(&counter())?.unwrap();
+        assert_eq!(node.inputs, vec![1, 1, 2, 1, 2]);
+        assert!(!node.halted);
+
+        // Some states are found more than once, but only explored once
+        assert!(explorer.explored() < 2 + 2 * 21);
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable_goal() -> Result<(), IntcodeError> {
+        let mut explorer = StateExplorer::new(&[1, 2], |node| node.output.clone(), |_| false);
+        assert!(explorer.search(&counter())?.is_none());
+
+        let mut explorer =
+            StateExplorer::new(&[1, 2], |node| node.inputs.clone(), |_| false).max_depth(3);
+        assert!(explorer.search(&counter())?.is_none());
+        assert_eq!(explorer.explored(), 1 + 2 + 4 + 8);
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+//! Recovering functions from the control flow graph.
+//!
+//! Intcode has no call instruction, but the puzzle programs all use the same convention: the
+//! caller stores its arguments at `base + 1`, `base + 2`, ..., the return address at
+//! `base + 0`, and jumps to the function. The function starts by pushing its frame with
+//! `REL_BASE += n`, so the return address ends up at `base - n` and the arguments right
+//! after it, and returns by popping the frame and jumping to `base + 0`.
+//!
+//! ```text
+//!    904: MUL 1 27 => &base + 1
+//!    908: MUL L915 1 => &base + 0
+//!    912: IF 1 JUMP TO L922  # call L922
+//! ...
+//! L922:
+//!    922: REL_BASE += 3  # function L922: frame 3, 1 argument (base + -2)
+//! ...
+//!    968: REL_BASE += -3
+//!    970: IF NOT 0 JUMP TO base + 0  # return from L922
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+use crate::cfg::{BasicBlock, Cfg, EdgeKind};
+use crate::computer::{Instruction, Param};
+use crate::disasm::{label, Disassembly};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Function {
+    pub entry: usize,
+    /// Start addresses of the basic blocks of the function.
+    pub blocks: BTreeSet<usize>,
+    /// What the function adds to the relative base on entry, if it sets up a frame.
+    pub frame_size: Option<i64>,
+    /// Offsets from the relative base, after the frame is set up, that the function reads
+    /// before writing them. These are the slots the caller filled in.
+    pub arguments: Vec<i64>,
+    /// Calls made by the function, as the address of the jump and the function called.
+    pub calls: BTreeMap<usize, usize>,
+    /// Addresses of the jumps that return to the caller.
+    pub returns: Vec<usize>,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "function {}: ", label(self.entry))?;
+        match self.frame_size {
+            Some(size) => write!(f, "frame {}", size)?,
+            None => write!(f, "no frame")?,
+        }
+        let arguments: Vec<String> = self
+            .arguments
+            .iter()
+            .map(|offset| Param::Relative(*offset).to_string())
+            .collect();
+        match arguments.len() {
+            0 => Ok(()),
+            1 => write!(f, ", 1 argument ({})", arguments[0]),
+            n => write!(f, ", {} arguments ({})", n, arguments.join(", ")),
+        }
+    }
+}
+
+/// The functions of a program by entry address: the code reached from address 0, and
+/// everything called.
+pub fn functions(cfg: &Cfg) -> BTreeMap<usize, Function> {
+    let mut entries: BTreeSet<usize> = cfg.blocks.values().filter_map(callee).collect();
+    if cfg.blocks.contains_key(&0) {
+        entries.insert(0);
+    }
+    entries
+        .iter()
+        .map(|&entry| (entry, function(cfg, &entries, entry)))
+        .collect()
+}
+
+/// Comment the entry, calls and returns of each function in the listing.
+pub fn annotate(disasm: &mut Disassembly, functions: &BTreeMap<usize, Function>) {
+    let mut add = |pc: usize, comment: String| {
+        disasm
+            .comments
+            .entry(pc)
+            .and_modify(|existing| *existing = format!("{}; {}", existing, comment))
+            .or_insert(comment);
+    };
+    for function in functions.values() {
+        add(function.entry, function.to_string());
+        for (&pc, &callee) in &function.calls {
+            add(pc, format!("call {}", label(callee)));
+        }
+        for &pc in &function.returns {
+            add(pc, format!("return from {}", label(function.entry)));
+        }
+    }
+}
+
+// The function a block calls, if it ends with a call
+fn callee(block: &BasicBlock) -> Option<usize> {
+    if !block
+        .successors
+        .iter()
+        .any(|edge| edge.kind == EdgeKind::CallReturn)
+    {
+        return None;
+    }
+    block
+        .successors
+        .iter()
+        .find(|edge| edge.kind == EdgeKind::Jump)
+        .map(|edge| edge.to)
+}
+
+// Where control goes next within the same function: over calls to the code after them,
+// and never into another function
+fn successors(block: &BasicBlock, entries: &BTreeSet<usize>, entry: usize) -> Vec<usize> {
+    let call = callee(block).is_some();
+    block
+        .successors
+        .iter()
+        .filter(|edge| match edge.kind {
+            EdgeKind::CallReturn => true,
+            _ if call => false,
+            _ => edge.to == entry || !entries.contains(&edge.to),
+        })
+        .map(|edge| edge.to)
+        .collect()
+}
+
+fn function(cfg: &Cfg, entries: &BTreeSet<usize>, entry: usize) -> Function {
+    let mut blocks = BTreeSet::new();
+    let mut todo = vec![entry];
+    while let Some(start) = todo.pop() {
+        if blocks.insert(start) {
+            todo.extend(successors(&cfg.blocks[&start], entries, entry));
+        }
+    }
+
+    let frame_size = match cfg.blocks[&entry].instrs.first() {
+        Some((
+            _,
+            Instruction::AdjustRelativeBase {
+                value: Param::Immediate(size),
+            },
+        )) if *size > 0 => Some(*size),
+        _ => None,
+    };
+
+    let mut calls = BTreeMap::new();
+    // Only a function with a frame knows where its return address is
+    let mut returns = Vec::new();
+    for start in &blocks {
+        let block = &cfg.blocks[start];
+        let (pc, last) = *block.instrs.last().unwrap();
+        if let Some(callee) = callee(block) {
+            calls.insert(pc, callee);
+        } else if let Some(edge) = block
+            .successors
+            .iter()
+            .find(|edge| edge.kind == EdgeKind::Jump && entries.contains(&edge.to))
+        {
+            // A jump straight into another function
+            if edge.to != entry {
+                calls.insert(pc, edge.to);
+            }
+        }
+        // Back through the return address, before or after the frame is popped
+        if let (
+            Some(size),
+            Instruction::JumpIfTrue {
+                jump_to: Param::Relative(offset),
+                ..
+            }
+            | Instruction::JumpIfFalse {
+                jump_to: Param::Relative(offset),
+                ..
+            },
+        ) = (frame_size, last)
+        {
+            if offset == 0 || offset == -size {
+                returns.push(pc);
+            }
+        }
+    }
+
+    let arguments = match frame_size {
+        Some(size) => arguments(cfg, entries, entry, &blocks, size),
+        None => Vec::new(),
+    };
+
+    Function {
+        entry,
+        blocks,
+        frame_size,
+        arguments,
+        calls,
+        returns,
+    }
+}
+
+// The slots of the caller's frame that are live on entry to the function, found by the usual
+// backward liveness analysis. Only the offsets between the return address and the new base
+// are tracked, everything else belongs to the function itself or to other frames.
+fn arguments(
+    cfg: &Cfg,
+    entries: &BTreeSet<usize>,
+    entry: usize,
+    blocks: &BTreeSet<usize>,
+    size: i64,
+) -> Vec<i64> {
+    let slot = |param: &Param| match param {
+        Param::Relative(offset) if -size < *offset && *offset < 0 => Some(*offset),
+        _ => None,
+    };
+
+    // Slots each block reads before writing, and slots it writes
+    let mut uses: BTreeMap<usize, BTreeSet<i64>> = BTreeMap::new();
+    let mut defs: BTreeMap<usize, BTreeSet<i64>> = BTreeMap::new();
+    for &start in blocks {
+        let mut used = BTreeSet::new();
+        let mut defined = BTreeSet::new();
+        for (index, (_, instr)) in cfg.blocks[&start].instrs.iter().enumerate() {
+            if let Instruction::AdjustRelativeBase { .. } = instr {
+                // The frame is set up by the first instruction of the function, any other
+                // change of the base means the offsets no longer point at it
+                if start == entry && index == 0 {
+                    continue;
+                }
+                break;
+            }
+            let (reads, write) = instr.operands();
+            for offset in reads.iter().filter_map(slot) {
+                if !defined.contains(&offset) {
+                    used.insert(offset);
+                }
+            }
+            if let Some(offset) = write.as_ref().and_then(slot) {
+                defined.insert(offset);
+            }
+        }
+        uses.insert(start, used);
+        defs.insert(start, defined);
+    }
+
+    let mut live_in: BTreeMap<usize, BTreeSet<i64>> = uses.clone();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &start in blocks.iter().rev() {
+            let mut live: BTreeSet<i64> = successors(&cfg.blocks[&start], entries, entry)
+                .into_iter()
+                .filter(|to| blocks.contains(to))
+                .flat_map(|to| live_in[&to].clone())
+                .filter(|offset| !defs[&start].contains(offset))
+                .collect();
+            live.extend(&uses[&start]);
+            if live != live_in[&start] {
+                live_in.insert(start, live);
+                changed = true;
+            }
+        }
+    }
+    live_in[&entry].iter().copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+
+    #[test]
+    fn recursive_function() {
+        let program = crate::parse_program(include_str!("../../aoc09/input/input.txt")).unwrap();
+        let functions = functions(&Cfg::new(&program));
+        let function = &functions[&922];
+        assert_eq!(function.frame_size, Some(3));
+        assert_eq!(function.arguments, vec![-2]);
+        assert_eq!(function.returns, vec![970]);
+        assert_eq!(
+            function
+                .calls
+                .iter()
+                .map(|(&pc, &to)| (pc, to))
+                .collect::<Vec<_>>(),
+            vec![(939, 922), (954, 922)]
+        );
+        assert_eq!(functions[&0].calls.get(&912), Some(&922));
+        assert!(!functions[&0].blocks.contains(&922));
+        assert!(functions[&0].blocks.contains(&915));
+        assert_eq!(
+            function.to_string(),
+            "function L922: frame 3, 1 argument (base + -2)"
+        );
+    }
+
+    #[test]
+    fn annotated_listing() {
+        let program = crate::asm::assemble(
+            "
+                ADD 5 0 => base + 1
+                ADD 7 0 => base + 2
+                ADD done 0 => base + 0
+                IF 1 JUMP TO add
+            done:
+                OUTPUT base + 1
+                HALT
+            add:
+                REL_BASE += 4
+                ADD base + -3 base + -2 => base + -1
+                ADD base + -1 0 => base + -3
+                REL_BASE += -4
+                IF 1 JUMP TO base + 0
+            ",
+        )
+        .unwrap();
+        let mut disasm = disassemble(&program);
+        let functions = functions(&Cfg::from_disassembly(&disasm));
+        assert_eq!(functions.keys().copied().collect::<Vec<_>>(), vec![0, 18]);
+        assert_eq!(functions[&18].arguments, vec![-3, -2]);
+        annotate(&mut disasm, &functions);
+        let listing = disasm.to_string();
+        assert!(listing.contains("     0: ADD 5 0 => &base + 1  # function L0: no frame\n"));
+        assert!(listing.contains("    12: IF 1 JUMP TO L18  # call L18\n"));
+        assert!(listing.contains(
+            "    18: REL_BASE += 4  # function L18: frame 4, 2 arguments (base + -3, base + -2)\n"
+        ));
+        assert!(listing.contains("    30: IF 1 JUMP TO base + 0  # return from L18\n"));
+    }
+}
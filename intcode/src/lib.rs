@@ -1,6 +1,28 @@
+pub mod ascii;
+pub mod asm;
+pub mod breakpoints;
+pub mod budget;
+pub mod cache;
+pub mod cfg;
 pub mod computer;
+pub mod disasm;
+pub mod error;
+pub mod explore;
+pub mod functions;
+pub mod memory;
+pub mod network;
+pub mod overflow;
+pub mod pipeline;
+pub mod profile;
+pub mod record;
+pub mod scheduler;
+pub mod snapshot;
+pub mod threaded;
+pub mod trace;
+pub mod transpile;
 
-pub use computer::{Computer, IO};
+pub use computer::{Computer, QueueIO, StopReason, IO};
+pub use error::IntcodeError;
 
 use anyhow::Result;
 
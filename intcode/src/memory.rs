@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+/// Words per page. Pages are allocated the first time something is written to them.
+const PAGE_SIZE: usize = 1024;
+
+/// Default upper bound on addressable memory, in words.
+pub const DEFAULT_LIMIT: usize = 1 << 24;
+
+/// Sparse, paged memory for the intcode computer. Reading a cell that was never written
+/// gives 0, and writing anywhere below `limit` transparently grows the memory.
+///
+/// Clones share their pages, and a page is only copied when one of them writes to it, so
+/// forking a computer costs next to nothing.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    pages: Vec<Option<Arc<[i64; PAGE_SIZE]>>>,
+    // one past the highest address that has been loaded or written
+    len: usize,
+    limit: usize,
+}
+
+impl Memory {
+    pub fn new(program: Vec<i64>) -> Memory {
+        Memory::with_limit(program, DEFAULT_LIMIT)
+    }
+
+    pub fn with_limit(program: Vec<i64>, limit: usize) -> Memory {
+        let mut memory = Memory {
+            pages: Vec::new(),
+            len: 0,
+            limit: limit.max(program.len()),
+        };
+        for (addr, value) in program.into_iter().enumerate() {
+            *memory.get_mut(addr).unwrap() = value;
+        }
+        memory
+    }
+
+    /// One past the highest address that has been loaded or written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Change the limit. Lowering it throws away the cells past the new limit, so raising it
+    /// again finds them zeroed.
+    pub fn set_limit(&mut self, limit: usize) {
+        if limit < self.len {
+            self.pages.truncate(limit.div_ceil(PAGE_SIZE));
+            if let Some(Some(page)) = self.pages.get_mut(limit / PAGE_SIZE) {
+                Arc::make_mut(page)[limit % PAGE_SIZE..]
+                    .iter_mut()
+                    .for_each(|cell| *cell = 0);
+            }
+            self.len = limit;
+        }
+        self.limit = limit;
+    }
+
+    /// Read a cell, or `None` if `addr` is past the limit.
+    pub fn get(&self, addr: usize) -> Option<i64> {
+        if addr >= self.limit {
+            return None;
+        }
+        Some(match self.pages.get(addr / PAGE_SIZE) {
+            Some(Some(page)) => page[addr % PAGE_SIZE],
+            _ => 0,
+        })
+    }
+
+    /// Get a mutable reference to a cell, allocating its page if needed, or copying it if it
+    /// is shared with a clone. `None` if `addr` is past the limit.
+    pub fn get_mut(&mut self, addr: usize) -> Option<&mut i64> {
+        if addr >= self.limit {
+            return None;
+        }
+        let page = addr / PAGE_SIZE;
+        if page >= self.pages.len() {
+            self.pages.resize_with(page + 1, || None);
+        }
+        self.len = self.len.max(addr + 1);
+        let page = self.pages[page].get_or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        Some(&mut Arc::make_mut(page)[addr % PAGE_SIZE])
+    }
+
+    /// Copy out the cells `0..len()`.
+    pub fn to_vec(&self) -> Vec<i64> {
+        (0..self.len).map(|addr| self.get(addr).unwrap()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untouched_cells_are_zero() {
+        let memory = Memory::new(vec![1, 2, 3]);
+        assert_eq!(memory.get(1), Some(2));
+        assert_eq!(memory.get(3), Some(0));
+        assert_eq!(memory.get(100_000), Some(0));
+        assert_eq!(memory.len(), 3);
+    }
+
+    #[test]
+    fn grows_on_write() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        *memory.get_mut(5000).unwrap() = 42;
+        assert_eq!(memory.get(5000), Some(42));
+        assert_eq!(memory.get(4999), Some(0));
+        assert_eq!(memory.len(), 5001);
+        assert_eq!(memory.to_vec()[..4], [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn clones_share_pages() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        *memory.get_mut(5000).unwrap() = 42;
+        let mut clone = memory.clone();
+        assert!(Arc::ptr_eq(
+            memory.pages[0].as_ref().unwrap(),
+            clone.pages[0].as_ref().unwrap()
+        ));
+        *clone.get_mut(1).unwrap() = 7;
+        assert_eq!(memory.get(1), Some(2));
+        assert_eq!(clone.get(1), Some(7));
+        assert!(!Arc::ptr_eq(
+            memory.pages[0].as_ref().unwrap(),
+            clone.pages[0].as_ref().unwrap()
+        ));
+        // Pages nobody wrote to are still shared
+        assert!(Arc::ptr_eq(
+            memory.pages[4].as_ref().unwrap(),
+            clone.pages[4].as_ref().unwrap()
+        ));
+    }
+
+    #[test]
+    fn respects_limit() {
+        let mut memory = Memory::with_limit(vec![1, 2, 3], 3);
+        assert_eq!(memory.get(2), Some(3));
+        assert_eq!(memory.get(3), None);
+        assert!(memory.get_mut(3).is_none());
+        assert_eq!(memory.to_vec(), vec![1, 2, 3]);
+
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        *memory.get_mut(5000).unwrap() = 42;
+        memory.set_limit(2);
+        assert_eq!(memory.len(), 2);
+        assert_eq!(memory.to_vec(), vec![1, 2]);
+        memory.set_limit(DEFAULT_LIMIT);
+        assert_eq!(memory.get(2), Some(0));
+        assert_eq!(memory.get(5000), Some(0));
+    }
+}
@@ -0,0 +1,333 @@
+//! A network of computers running the same program, sending each other packets.
+//!
+//! Each computer gets its address as its first input. After that it sends a packet by
+//! outputting the destination address, X and Y, and receives one by reading X and Y. When
+//! nothing has arrived for it, reading gives -1.
+//!
+//! The computers take turns, each running until it asks for input again with nothing
+//! queued, so a network always runs the same way. Packets to the addresses that are being
+//! monitored, like the NAT at 255, are handed to the caller instead of being delivered:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), intcode::network::NetworkError> {
+//! use intcode::network::{Network, NetworkEvent, Packet};
+//!
+//! # let program = vec![99];
+//! let mut network = Network::new(&program, 50);
+//! network.monitor(255);
+//! let mut nat = None;
+//! loop {
+//!     match network.run()? {
+//!         NetworkEvent::Packet(packet) => nat = Some(packet),
+//!         NetworkEvent::Idle => match nat {
+//!             Some(Packet { x, y, .. }) => network.send(Packet { from: 255, to: 0, x, y })?,
+//!             None => break,
+//!         },
+//!         NetworkEvent::Halted => break,
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeSet, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::computer::{Computer, StopReason};
+use crate::error::IntcodeError;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Packet {
+    pub from: i64,
+    pub to: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Why `Network::run` returned.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum NetworkEvent {
+    /// A packet was sent to a monitored address.
+    Packet(Packet),
+    /// Every computer is waiting for input, and no packets are on their way.
+    Idle,
+    /// Every computer has halted.
+    Halted,
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    /// The computer at `address` failed.
+    Computer {
+        address: i64,
+        source: IntcodeError,
+    },
+    /// A packet was sent to an address without a computer, that isn't monitored either.
+    UnknownAddress(Packet),
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Computer { address, source } => {
+                write!(f, "Computer {} failed: {}", address, source)
+            }
+            NetworkError::UnknownAddress(packet) => write!(
+                f,
+                "Packet ({}, {}) from {} to unknown address {}",
+                packet.x, packet.y, packet.from, packet.to
+            ),
+        }
+    }
+}
+
+impl Error for NetworkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NetworkError::Computer { source, .. } => Some(source),
+            NetworkError::UnknownAddress(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    computer: Computer,
+    // packets waiting to be read
+    queue: VecDeque<(i64, i64)>,
+    // the start of a packet being sent
+    sending: Vec<i64>,
+    halted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Network {
+    nodes: Vec<Node>,
+    monitored: BTreeSet<i64>,
+    // the computer whose turn it is
+    current: usize,
+    // whether the current computer has been given its input for this turn
+    in_turn: bool,
+    // whether any packet was sent during this round of turns
+    busy: bool,
+}
+
+impl Network {
+    /// Boot `size` computers running `program`, with addresses `0..size`.
+    pub fn new(program: &[i64], size: usize) -> Network {
+        let nodes = (0..size)
+            .map(|address| {
+                let mut computer = Computer::from_mem(program.to_vec());
+                computer.push_input(address as i64);
+                Node {
+                    computer,
+                    queue: VecDeque::new(),
+                    sending: Vec::new(),
+                    halted: false,
+                }
+            })
+            .collect();
+        Network {
+            nodes,
+            monitored: BTreeSet::new(),
+            current: 0,
+            in_turn: false,
+            // The computers haven't done anything yet
+            busy: true,
+        }
+    }
+
+    /// Hand packets sent to `address` to the caller of `run`, rather than delivering them.
+    pub fn monitor(&mut self, address: i64) {
+        self.monitored.insert(address);
+    }
+
+    pub fn computer(&self, address: usize) -> Option<&Computer> {
+        self.nodes.get(address).map(|node| &node.computer)
+    }
+
+    /// Deliver `packet` to a computer, from outside the network.
+    pub fn send(&mut self, packet: Packet) -> Result<(), NetworkError> {
+        self.busy = true;
+        match self.nodes.get_mut(packet.to as usize) {
+            Some(node) if packet.to >= 0 => {
+                node.queue.push_back((packet.x, packet.y));
+                Ok(())
+            }
+            _ => Err(NetworkError::UnknownAddress(packet)),
+        }
+    }
+
+    /// Run the computers until a packet is sent to a monitored address, the network goes
+    /// idle, or everything has halted. Calling this again continues where it stopped.
+    pub fn run(&mut self) -> Result<NetworkEvent, NetworkError> {
+        loop {
+            if self.nodes.iter().all(|node| node.halted) {
+                return Ok(NetworkEvent::Halted);
+            }
+            if let Some(packet) = self.turn()? {
+                return Ok(NetworkEvent::Packet(packet));
+            }
+            self.current += 1;
+            if self.current == self.nodes.len() {
+                self.current = 0;
+                let waiting = self.nodes.iter().any(|node| !node.queue.is_empty());
+                if !self.busy && !waiting {
+                    return Ok(NetworkEvent::Idle);
+                }
+                self.busy = false;
+            }
+        }
+    }
+
+    // Run the current computer until it waits for input, or sends a packet to a monitored
+    // address
+    fn turn(&mut self) -> Result<Option<Packet>, NetworkError> {
+        let address = self.current as i64;
+        let node = &mut self.nodes[self.current];
+        if node.halted {
+            return Ok(None);
+        }
+        if !self.in_turn {
+            self.in_turn = true;
+            if node.queue.is_empty() {
+                node.computer.push_input(-1);
+            }
+            for (x, y) in node.queue.drain(..) {
+                node.computer.push_input(x);
+                node.computer.push_input(y);
+            }
+        }
+        loop {
+            let node = &mut self.nodes[self.current];
+            let reason = node
+                .computer
+                .resume()
+                .map_err(|source| NetworkError::Computer { address, source })?;
+            match reason {
+                StopReason::ProducedOutput(value) => {
+                    node.sending.push(value);
+                    if node.sending.len() < 3 {
+                        continue;
+                    }
+                    let packet = Packet {
+                        from: address,
+                        to: node.sending[0],
+                        x: node.sending[1],
+                        y: node.sending[2],
+                    };
+                    node.sending.clear();
+                    if self.monitored.contains(&packet.to) {
+                        self.busy = true;
+                        return Ok(Some(packet));
+                    }
+                    self.send(packet)?;
+                }
+                StopReason::NeedsInput => break,
+                StopReason::Halted => {
+                    node.halted = true;
+                    break;
+                }
+                StopReason::Breakpoint | StopReason::Watchpoint { .. } => {}
+            }
+        }
+        self.in_turn = false;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Forward each packet to the next address, adding one to Y
+    fn relay() -> Vec<i64> {
+        crate::asm::assemble(
+            "
+                INPUT &address
+                ADD &address 1 => &next
+            receive:
+                INPUT &x
+                IF &x == -1 => &empty
+                IF &empty JUMP TO receive
+                INPUT &y
+                OUTPUT &next
+                OUTPUT &x
+                ADD &y 1 => &y
+                OUTPUT &y
+                IF 1 JUMP TO receive
+            address: DATA 0
+            next: DATA 0
+            x: DATA 0
+            y: DATA 0
+            empty: DATA 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn routes_packets() -> Result<(), NetworkError> {
+        let mut network = Network::new(&relay(), 3);
+        network.monitor(3);
+        assert_eq!(network.run()?, NetworkEvent::Idle);
+        network.send(Packet {
+            from: 255,
+            to: 0,
+            x: 7,
+            y: 0,
+        })?;
+        assert_eq!(
+            network.run()?,
+            NetworkEvent::Packet(Packet {
+                from: 2,
+                to: 3,
+                x: 7,
+                y: 3
+            })
+        );
+        assert_eq!(network.run()?, NetworkEvent::Idle);
+        Ok(())
+    }
+
+    #[test]
+    fn monitor_computer() -> Result<(), NetworkError> {
+        let mut network = Network::new(&relay(), 3);
+        network.monitor(1);
+        network.send(Packet {
+            from: 255,
+            to: 0,
+            x: 7,
+            y: 0,
+        })?;
+        assert_eq!(
+            network.run()?,
+            NetworkEvent::Packet(Packet {
+                from: 0,
+                to: 1,
+                x: 7,
+                y: 1
+            })
+        );
+        assert_eq!(network.run()?, NetworkEvent::Idle);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_address() {
+        let mut network = Network::new(&relay(), 2);
+        network
+            .send(Packet {
+                from: 255,
+                to: 1,
+                x: 7,
+                y: 0,
+            })
+            .unwrap();
+        match network.run() {
+            Err(NetworkError::UnknownAddress(Packet { from: 1, to: 2, .. })) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+
+use crate::IntcodeError;
+
+/// What ADD and MULTIPLY do when the result doesn't fit in an `i64`. Whichever is chosen,
+/// every backend behaves the same, in debug and release builds alike.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum Overflow {
+    /// Wrap around, like two's complement hardware. This is the default.
+    #[default]
+    Wrapping,
+    /// Stop with `IntcodeError::ArithmeticOverflow`, pointing at the instruction.
+    Checked,
+    /// Clamp to `i64::MIN` or `i64::MAX`.
+    Saturating,
+    /// Compute in `i128`, where ADD and MULTIPLY of two words never overflow, and stop with
+    /// `IntcodeError::WordOverflow` when the exact result is stored and doesn't fit in memory.
+    Wide,
+}
+
+impl Overflow {
+    /// `a + b`, or the exact result if it overflows and that is an error.
+    pub fn add(self, a: i64, b: i64) -> Result<i64, i128> {
+        match self {
+            Overflow::Wrapping => Ok(a.wrapping_add(b)),
+            Overflow::Checked | Overflow::Wide => {
+                a.checked_add(b).ok_or_else(|| a as i128 + b as i128)
+            }
+            Overflow::Saturating => Ok(a.saturating_add(b)),
+        }
+    }
+
+    /// `a * b`, or the exact result if it overflows and that is an error.
+    pub fn mul(self, a: i64, b: i64) -> Result<i64, i128> {
+        match self {
+            Overflow::Wrapping => Ok(a.wrapping_mul(b)),
+            Overflow::Checked | Overflow::Wide => {
+                a.checked_mul(b).ok_or_else(|| a as i128 * b as i128)
+            }
+            Overflow::Saturating => Ok(a.saturating_mul(b)),
+        }
+    }
+
+    /// The error for an instruction whose exact result is `value`, which doesn't fit.
+    pub fn error(self, pc: usize, opcode: i64, value: i128) -> IntcodeError {
+        match self {
+            Overflow::Wide => IntcodeError::WordOverflow { pc, opcode, value },
+            _ => IntcodeError::ArithmeticOverflow { pc, opcode },
+        }
+    }
+}
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Overflow::Wrapping => write!(f, "wrapping"),
+            Overflow::Checked => write!(f, "checked"),
+            Overflow::Saturating => write!(f, "saturating"),
+            Overflow::Wide => write!(f, "wide"),
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrapping" => Ok(Overflow::Wrapping),
+            "checked" => Ok(Overflow::Checked),
+            "saturating" => Ok(Overflow::Saturating),
+            "wide" => Ok(Overflow::Wide),
+            _ => Err(anyhow!(
+                "Unknown overflow policy {:?}, expected wrapping, checked, saturating or wide",
+                s
+            )),
+        }
+    }
+}
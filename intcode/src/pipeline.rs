@@ -0,0 +1,293 @@
+//! Computers connected by channels and spawned as tasks, like the day 7 amplifiers, but
+//! watched for deadlocks: when every computer that is still running waits for input that
+//! nobody is sending, `Pipeline::run` fails with the computers and the pcs they are stuck
+//! at, instead of hanging forever.
+//!
+//! To know for sure that nothing more is coming, all input has to come from the computers
+//! in the pipeline, or be queued before it starts with `Pipeline::push_input`.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use async_std::prelude::*;
+use async_std::sync::{channel, Receiver, Sender};
+use async_std::task;
+
+use crate::computer::Computer;
+use crate::error::{Deadlock, EndOfInput, IntcodeError};
+use crate::scheduler::SchedulerError;
+
+// What the IO of a stuck computer returns, once the deadlock has been found
+#[derive(Debug)]
+struct Stuck;
+
+impl Display for Stuck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Deadlocked while waiting for input")
+    }
+}
+
+impl Error for Stuck {}
+
+#[derive(Debug, Default)]
+struct Status {
+    output_to: Option<usize>,
+    waiting: bool,
+    halted: bool,
+    // values sent to this computer that it hasn't received yet
+    in_flight: usize,
+    output: Vec<i64>,
+}
+
+#[derive(Debug)]
+struct State {
+    computers: Vec<Status>,
+    // dropped to wake up every waiting computer when there is a deadlock
+    alarm: Option<Sender<()>>,
+}
+
+impl State {
+    fn check(&mut self) {
+        let stuck = |status: &Status| status.waiting && status.in_flight == 0;
+        if self.computers.iter().any(stuck)
+            && self
+                .computers
+                .iter()
+                .all(|status| status.halted || stuck(status))
+        {
+            self.alarm = None;
+        }
+    }
+}
+
+/// The part of a `ChannelIO` that tells the pipeline what its computer is doing.
+#[derive(Debug, Clone)]
+pub(crate) struct Watch {
+    computer: usize,
+    state: Arc<Mutex<State>>,
+    alarm: Receiver<()>,
+}
+
+impl Watch {
+    pub async fn input(&self, input: &Receiver<i64>) -> Result<i64> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.computers[self.computer].waiting = true;
+            state.check();
+        }
+        let value = async { Some(input.recv().await) }
+            .race(async {
+                self.alarm.recv().await;
+                None
+            })
+            .await;
+        let mut state = self.state.lock().unwrap();
+        let deadlocked = state.alarm.is_none();
+        let status = &mut state.computers[self.computer];
+        status.waiting = false;
+        match value {
+            Some(Some(value)) => {
+                status.in_flight -= 1;
+                Ok(value)
+            }
+            // The input may also have been closed by another stuck computer giving up
+            Some(None) if !deadlocked => Err(EndOfInput.into()),
+            _ => Err(anyhow!(Stuck)),
+        }
+    }
+
+    pub fn output(&self, value: i64) {
+        let mut state = self.state.lock().unwrap();
+        let status = &mut state.computers[self.computer];
+        status.output.push(value);
+        if let Some(to) = status.output_to {
+            // Nobody receives what is sent to a computer that has halted
+            if !state.computers[to].halted {
+                state.computers[to].in_flight += 1;
+            }
+        }
+    }
+
+    fn halted(&self) {
+        let mut state = self.state.lock().unwrap();
+        let status = &mut state.computers[self.computer];
+        status.halted = true;
+        status.in_flight = 0;
+        state.check();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    computers: Vec<Computer>,
+    output_to: Vec<Option<usize>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a computer. Returns its number.
+    pub fn add(&mut self, computer: Computer) -> usize {
+        self.computers.push(computer);
+        self.output_to.push(None);
+        self.computers.len() - 1
+    }
+
+    /// Send the output of computer `from` to the input of computer `to`.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.output_to[from] = Some(to);
+    }
+
+    pub fn push_input(&mut self, computer: usize, value: i64) {
+        self.computers[computer].push_input(value);
+    }
+
+    /// Spawn all the computers, and wait for them to halt. Returns what each computer
+    /// output, whether it was sent on to another computer or not. Fails the same way as
+    /// `Scheduler::run`.
+    pub async fn run(self) -> Result<Vec<Vec<i64>>, SchedulerError> {
+        let (alarm_sender, alarm) = channel(1);
+        let state = Arc::new(Mutex::new(State {
+            computers: self
+                .output_to
+                .iter()
+                .map(|&output_to| Status {
+                    output_to,
+                    ..Status::default()
+                })
+                .collect(),
+            alarm: Some(alarm_sender),
+        }));
+        let channels: Vec<(Sender<i64>, Receiver<i64>)> =
+            self.computers.iter().map(|_| channel(1)).collect();
+
+        let mut tasks = Vec::new();
+        for (index, mut computer) in self.computers.into_iter().enumerate() {
+            computer.connect_input(channels[index].1.clone());
+            if let Some(to) = self.output_to[index] {
+                computer.connect_output(channels[to].0.clone());
+            }
+            let watch = Watch {
+                computer: index,
+                state: state.clone(),
+                alarm: alarm.clone(),
+            };
+            computer.set_watch(watch.clone());
+            tasks.push(task::spawn(async move {
+                let result = computer.run().await;
+                // Before dropping the computer closes its output, so whoever waits for it
+                // sees why nothing more is coming
+                watch.halted();
+                result
+            }));
+        }
+        // Only the computers may send anything now
+        drop(channels);
+
+        let mut blocked = Vec::new();
+        let mut failed = None;
+        for (index, task) in tasks.into_iter().enumerate() {
+            match task.await {
+                Ok(()) => {}
+                Err(IntcodeError::Io { pc, source })
+                    if source.downcast_ref::<Stuck>().is_some() =>
+                {
+                    blocked.push((index, pc))
+                }
+                Err(source) => {
+                    failed.get_or_insert(SchedulerError::Computer {
+                        computer: index,
+                        source,
+                    });
+                }
+            }
+        }
+        if let Some(err) = failed {
+            return Err(err);
+        }
+        if !blocked.is_empty() {
+            return Err(SchedulerError::Deadlock(Deadlock { blocked }));
+        }
+        let mut state = state.lock().unwrap();
+        Ok(state
+            .computers
+            .iter_mut()
+            .map(|status| std::mem::take(&mut status.output))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Adds one to the input and outputs it, until it gets to 10
+    fn counter() -> Computer {
+        let program = crate::asm::assemble(
+            "
+            loop:
+                INPUT &x
+                IF &x < 10 => &more
+                IF NOT &more JUMP TO end
+                ADD &x 1 => &x
+                OUTPUT &x
+                IF &x < 10 => &more
+                IF &more JUMP TO loop
+            end:
+                HALT
+            x: DATA 0
+            more: DATA 0
+            ",
+        )
+        .unwrap();
+        Computer::from_mem(program)
+    }
+
+    #[async_std::test]
+    async fn ping_pong() -> Result<(), SchedulerError> {
+        let mut pipeline = Pipeline::new();
+        let ping = pipeline.add(counter());
+        let pong = pipeline.add(counter());
+        pipeline.connect(ping, pong);
+        pipeline.connect(pong, ping);
+        pipeline.push_input(ping, 0);
+        let output = pipeline.run().await?;
+        assert_eq!(output, vec![vec![1, 3, 5, 7, 9], vec![2, 4, 6, 8, 10]]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn waiting_for_each_other() {
+        let mut pipeline = Pipeline::new();
+        let first = pipeline.add(counter());
+        let second = pipeline.add(counter());
+        pipeline.connect(first, second);
+        pipeline.connect(second, first);
+        match pipeline.run().await {
+            Err(SchedulerError::Deadlock(Deadlock { blocked })) => {
+                assert_eq!(blocked, vec![(0, 0), (1, 0)])
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[async_std::test]
+    async fn starved_after_halt() {
+        // Nothing more can come once the second amplifier halts
+        let mut pipeline = Pipeline::new();
+        let first = pipeline.add(Computer::from_mem(vec![3, 20, 3, 20, 3, 20, 99]));
+        let second = pipeline.add(Computer::from_mem(vec![104, 1, 99]));
+        pipeline.connect(second, first);
+        pipeline.push_input(first, 1);
+        match pipeline.run().await {
+            Err(SchedulerError::Deadlock(Deadlock { blocked })) => {
+                assert_eq!(blocked, vec![(0, 4)])
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Counting where a program spends its time. Enable with `Computer::enable_profile`, run
+//! the program, and look at the `Profile`.
+//!
+//! Besides plain counts per pc and opcode the profile keeps a call stack, so it can be
+//! written in the folded format read by flamegraph tools. Intcode has no call instruction,
+//! so a call is recognized the same way as in the disassembler: an unconditional jump right
+//! after an instruction that stores its return address. Jumping to a return address on the
+//! stack returns to that frame.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use crate::computer::Instruction;
+use crate::disasm::{constant, is_unconditional, label};
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// Instructions executed in total.
+    pub steps: u64,
+    /// Times each address was executed.
+    pub pcs: HashMap<usize, u64>,
+    /// Times each opcode was executed, by opcode without the parameter modes.
+    pub opcodes: BTreeMap<i64, u64>,
+    /// Steps spent in each call stack. Frames are the jump targets of the calls.
+    pub stacks: HashMap<Vec<usize>, u64>,
+    // Current call stack: (target, return address)
+    frames: Vec<(usize, usize)>,
+    current_stack: Vec<usize>,
+    // What the last instruction stored, if it was a constant
+    last_constant: Option<i64>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count `instr`, which was executed at `pc` and continued at `next_pc`.
+    pub fn record(&mut self, pc: usize, opcode: i64, instr: &Instruction, next_pc: usize) {
+        self.steps += 1;
+        *self.pcs.entry(pc).or_insert(0) += 1;
+        *self.opcodes.entry(opcode % 100).or_insert(0) += 1;
+        match self.stacks.get_mut(&self.current_stack[..]) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.current_stack.clone(), 1);
+            }
+        }
+
+        let return_address = (pc + instr.len()) as i64;
+        if is_unconditional(instr) && self.last_constant == Some(return_address) {
+            self.frames.push((next_pc, return_address as usize));
+            self.current_stack.push(next_pc);
+        } else if let Some(depth) = self
+            .frames
+            .iter()
+            .rposition(|&(_, return_address)| return_address == next_pc)
+        {
+            self.frames.truncate(depth);
+            self.current_stack.truncate(depth);
+        }
+        self.last_constant = constant(instr);
+    }
+
+    /// The `top` most executed addresses with their counts, most executed first.
+    pub fn hottest(&self, top: usize) -> Vec<(usize, u64)> {
+        let mut pcs: Vec<(usize, u64)> = self.pcs.iter().map(|(&pc, &n)| (pc, n)).collect();
+        pcs.sort_by_key(|&(pc, n)| (std::cmp::Reverse(n), pc));
+        pcs.truncate(top);
+        pcs
+    }
+
+    /// A human readable report of the opcodes and the `top` hottest addresses, annotated
+    /// with the instruction found there in `program`.
+    pub fn report(&self, program: &[i64], top: usize) -> String {
+        let percent = |n: u64| 100.0 * n as f64 / self.steps.max(1) as f64;
+        let mut report = format!("Total steps: {}\n\nOpcodes:\n", self.steps);
+        let mut opcodes: Vec<(i64, u64)> = self.opcodes.iter().map(|(&op, &n)| (op, n)).collect();
+        opcodes.sort_by_key(|&(op, n)| (std::cmp::Reverse(n), op));
+        for (opcode, n) in opcodes {
+            report += &format!(
+                "  {:<14} {:>12} {:>6.2}%\n",
+                opcode_name(opcode),
+                n,
+                percent(n)
+            );
+        }
+        report += "\nHot addresses:\n";
+        for (pc, n) in self.hottest(top) {
+            let instr = match Instruction::decode(program, pc) {
+                Ok(instr) => instr.to_string(),
+                Err(err) => format!("<{}>", err),
+            };
+            report += &format!("  {:>12} {:>6.2}%  {:>6}: {}\n", n, percent(n), pc, instr);
+        }
+        report
+    }
+
+    /// Write the call stacks in the folded format, one `main;L12;L40 count` line per stack.
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut stacks: Vec<(String, u64)> = self
+            .stacks
+            .iter()
+            .map(|(stack, &n)| {
+                let frames =
+                    std::iter::once("main".to_string()).chain(stack.iter().map(|&pc| label(pc)));
+                (frames.collect::<Vec<_>>().join(";"), n)
+            })
+            .collect();
+        stacks.sort();
+        for (stack, n) in stacks {
+            writeln!(writer, "{} {}", stack, n)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn opcode_name(opcode: i64) -> &'static str {
+    match opcode % 100 {
+        1 => "ADD",
+        2 => "MUL",
+        3 => "INPUT",
+        4 => "OUTPUT",
+        5 => "JUMP_IF_TRUE",
+        6 => "JUMP_IF_FALSE",
+        7 => "LESS_THAN",
+        8 => "EQUALS",
+        9 => "REL_BASE",
+        99 => "HALT",
+        _ => "UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Computer, StopReason};
+
+    #[test]
+    fn counts_steps() -> anyhow::Result<()> {
+        // Count memory[20] up to 5
+        let program = vec![1001, 20, 1, 20, 1007, 20, 5, 21, 1005, 21, 0, 99];
+        let mut computer = Computer::from_mem(program.clone());
+        computer.enable_profile();
+        assert_eq!(computer.resume()?, StopReason::Halted);
+        let profile = computer.take_profile().unwrap();
+        assert_eq!(profile.steps, 16);
+        assert_eq!(profile.pcs[&0], 5);
+        assert_eq!(profile.pcs[&11], 1);
+        assert_eq!(profile.opcodes[&1], 5);
+        assert_eq!(profile.opcodes[&5], 5);
+        assert_eq!(profile.hottest(1), vec![(0, 5)]);
+        assert!(profile
+            .report(&program, 3)
+            .contains("5  31.25%       0: ADD &20 1 => &&20"));
+        Ok(())
+    }
+
+    #[test]
+    fn folded_stacks() -> anyhow::Result<()> {
+        let program = crate::asm::assemble(
+            "
+                ADD done 0 => base + 0
+                IF 1 JUMP TO function
+            done:
+                HALT
+            function:
+                ADD 1 2 => &100
+                IF 1 JUMP TO base + 0
+            ",
+        )?;
+        let mut computer = Computer::from_mem(program);
+        computer.enable_profile();
+        assert_eq!(computer.resume()?, StopReason::Halted);
+        let mut folded = Vec::new();
+        computer.profile().unwrap().write_folded(&mut folded)?;
+        assert_eq!(String::from_utf8(folded)?, "main 3\nmain;L8 2\n");
+        Ok(())
+    }
+}
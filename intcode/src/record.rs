@@ -0,0 +1,288 @@
+//! Recording the IO of a session and replaying it. Wrap the IO of an interactive program,
+//! like a game played by a controller, in a `Recorder`, save the `Recording`, and later run
+//! the program with a `Replay` of it: it gives the program the same input, and fails as
+//! soon as the output differs. That makes a regression test that needs neither a window
+//! nor a live controller.
+//!
+//! A recording is stored as JSON lines, one value per line:
+//!
+//! ```text
+//! {"output":42}
+//! {"input":-1}
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::computer::IO;
+use crate::error::EndOfInput;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoEvent {
+    Input(i64),
+    Output(i64),
+}
+
+impl Display for IoEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoEvent::Input(value) => write!(f, "input {}", value),
+            IoEvent::Output(value) => write!(f, "output {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Recording {
+    pub events: Vec<IoEvent>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> Result<()> {
+        for event in &self.events {
+            serde_json::to_writer(&mut writer, event)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_jsonl<R: BufRead>(reader: R) -> Result<Recording> {
+        let events = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|(index, line)| {
+                serde_json::from_str(&line?)
+                    .with_context(|| format!("Invalid IO event on line {}", index + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Recording { events })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_jsonl(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording> {
+        Recording::read_jsonl(BufReader::new(File::open(path)?))
+    }
+}
+
+/// An `IO` that passes everything on to `inner`, and records it.
+#[derive(Debug, Clone)]
+pub struct Recorder<I> {
+    pub inner: I,
+    pub recording: Recording,
+}
+
+impl<I: IO> Recorder<I> {
+    pub fn new(inner: I) -> Self {
+        Recorder {
+            inner,
+            recording: Recording::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<I: IO> IO for Recorder<I> {
+    async fn input(&mut self) -> Result<i64> {
+        let value = self.inner.input().await?;
+        self.recording.events.push(IoEvent::Input(value));
+        Ok(value)
+    }
+
+    async fn output(&mut self, data: i64) -> Result<()> {
+        self.recording.events.push(IoEvent::Output(data));
+        self.inner.output(data).await
+    }
+}
+
+/// The replayed program did something else than what was recorded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Mismatch {
+    /// Index of the event in the recording.
+    pub index: usize,
+    /// The recorded event, `None` if the recording has ended.
+    pub expected: Option<IoEvent>,
+    /// What the program did instead. For input, the value is meaningless.
+    pub actual: IoEvent,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let actual = match self.actual {
+            IoEvent::Input(_) => "asked for input".to_string(),
+            actual => actual.to_string(),
+        };
+        match self.expected {
+            Some(expected) => write!(
+                f,
+                "Replay differs at event {}: expected {}, but the program {}",
+                self.index, expected, actual
+            ),
+            None => write!(
+                f,
+                "Replay differs at event {}: the recording has ended, but the program {}",
+                self.index, actual
+            ),
+        }
+    }
+}
+
+impl Error for Mismatch {}
+
+/// An `IO` that plays back a recording: it gives the program the recorded input, and
+/// fails with a `Mismatch` when the program does anything else than what was recorded.
+/// Wanting input after the end of the recording is `IntcodeError::InputClosed`, like for
+/// other IO that runs out of input.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    events: VecDeque<IoEvent>,
+    index: usize,
+}
+
+impl Replay {
+    pub fn new(recording: &Recording) -> Self {
+        Replay {
+            events: recording.events.iter().copied().collect(),
+            index: 0,
+        }
+    }
+
+    /// The recorded events the program hasn't got to. A program that halts before the
+    /// recording ended did not do everything it did before.
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    fn next(&mut self, actual: IoEvent) -> Result<IoEvent> {
+        let expected = self.events.front().copied();
+        let matches = match (expected, actual) {
+            (Some(IoEvent::Input(_)), IoEvent::Input(_)) => true,
+            (Some(expected), actual) => expected == actual,
+            (None, _) => false,
+        };
+        if !matches {
+            return Err(Mismatch {
+                index: self.index,
+                expected,
+                actual,
+            }
+            .into());
+        }
+        self.index += 1;
+        Ok(self.events.pop_front().unwrap())
+    }
+}
+
+#[async_trait]
+impl IO for Replay {
+    async fn input(&mut self) -> Result<i64> {
+        if self.events.is_empty() {
+            return Err(EndOfInput.into());
+        }
+        match self.next(IoEvent::Input(0))? {
+            IoEvent::Input(value) => Ok(value),
+            IoEvent::Output(_) => unreachable!(),
+        }
+    }
+
+    async fn output(&mut self, data: i64) -> Result<()> {
+        self.next(IoEvent::Output(data))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, IntcodeError, QueueIO};
+
+    // Output the sum of each pair of inputs, until given a zero
+    fn adder() -> Computer {
+        let program = crate::asm::assemble(
+            "
+            loop:
+                INPUT &a
+                IF NOT &a JUMP TO end
+                INPUT &b
+                ADD &a &b => &sum
+                OUTPUT &sum
+                IF 1 JUMP TO loop
+            end:
+                HALT
+            a: DATA 0
+            b: DATA 0
+            sum: DATA 0
+            ",
+        )
+        .unwrap();
+        Computer::from_mem(program)
+    }
+
+    #[async_std::test]
+    async fn record_and_replay() -> Result<()> {
+        let mut computer = adder().with_io(Recorder::new(QueueIO::new(&[1, 2, 3, 4, 0])));
+        computer.run().await?;
+        let recording = computer.into_io().recording;
+        assert_eq!(
+            recording.events,
+            vec![
+                IoEvent::Input(1),
+                IoEvent::Input(2),
+                IoEvent::Output(3),
+                IoEvent::Input(3),
+                IoEvent::Input(4),
+                IoEvent::Output(7),
+                IoEvent::Input(0),
+            ]
+        );
+
+        let mut jsonl = Vec::new();
+        recording.write_jsonl(&mut jsonl)?;
+        assert!(String::from_utf8(jsonl.clone())?.starts_with("{\"input\":1}\n"));
+        let recording = Recording::read_jsonl(&jsonl[..])?;
+
+        let mut computer = adder().with_io(Replay::new(&recording));
+        computer.run().await?;
+        assert_eq!(computer.into_io().remaining(), 0);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn replay_mismatch() -> Result<()> {
+        let recording = Recording {
+            events: vec![IoEvent::Input(1), IoEvent::Input(2), IoEvent::Output(4)],
+        };
+        let mut computer = adder().with_io(Replay::new(&recording));
+        match computer.run().await {
+            Err(IntcodeError::Io { pc: 13, source }) => assert_eq!(
+                source.downcast_ref::<Mismatch>(),
+                Some(&Mismatch {
+                    index: 2,
+                    expected: Some(IoEvent::Output(4)),
+                    actual: IoEvent::Output(3),
+                })
+            ),
+            other => panic!("Unexpected result {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Running several computers on one thread, taking turns. Unlike spawning them, this always
+//! interleaves their IO the same way, which makes setups like the day 7 feedback loop easy
+//! to debug.
+//!
+//! A computer runs until it needs input that hasn't arrived yet, or halts, and then the
+//! next one gets a turn. Output goes straight to the input queue of the computer it is
+//! connected to. If a whole round goes by without any computer getting anywhere, they are
+//! all waiting for each other, and `run` reports the deadlock.
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::computer::{Computer, StopReason};
+use crate::error::{Deadlock, IntcodeError};
+
+#[derive(Debug)]
+pub enum SchedulerError {
+    /// Computer number `computer` failed.
+    Computer {
+        computer: usize,
+        source: IntcodeError,
+    },
+    Deadlock(Deadlock),
+}
+
+impl Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchedulerError::Computer { computer, source } => {
+                write!(f, "Computer {} failed: {}", computer, source)
+            }
+            SchedulerError::Deadlock(deadlock) => write!(f, "{}", deadlock),
+        }
+    }
+}
+
+impl Error for SchedulerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SchedulerError::Computer { source, .. } => Some(source),
+            SchedulerError::Deadlock(deadlock) => Some(deadlock),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Task {
+    computer: Computer,
+    // where output goes, `None` to keep it in `output`
+    output_to: Option<usize>,
+    output: Vec<i64>,
+    halted: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    /// Every value output, in the order it happened, with the computer that output it.
+    pub history: Vec<(usize, i64)>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a computer, which runs after the ones already added. Returns its number.
+    pub fn add(&mut self, computer: Computer) -> usize {
+        self.tasks.push(Task {
+            computer,
+            output_to: None,
+            output: Vec::new(),
+            halted: false,
+        });
+        self.tasks.len() - 1
+    }
+
+    /// Send the output of computer `from` to the input of computer `to`.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.tasks[from].output_to = Some(to);
+    }
+
+    pub fn push_input(&mut self, computer: usize, value: i64) {
+        self.tasks[computer].computer.push_input(value);
+    }
+
+    /// What `computer` output, if it isn't connected to another computer.
+    pub fn output(&self, computer: usize) -> &[i64] {
+        &self.tasks[computer].output
+    }
+
+    pub fn computer(&self, computer: usize) -> &Computer {
+        &self.tasks[computer].computer
+    }
+
+    /// Run until every computer has halted.
+    pub fn run(&mut self) -> Result<(), SchedulerError> {
+        loop {
+            let mut progress = false;
+            for index in 0..self.tasks.len() {
+                if self.tasks[index].halted {
+                    continue;
+                }
+                let steps = self.tasks[index].computer.steps();
+                self.turn(index)?;
+                progress |= self.tasks[index].computer.steps() != steps;
+            }
+            if self.tasks.iter().all(|task| task.halted) {
+                return Ok(());
+            }
+            if !progress {
+                let blocked = self
+                    .tasks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, task)| !task.halted)
+                    .map(|(index, task)| (index, task.computer.pc()))
+                    .collect();
+                return Err(SchedulerError::Deadlock(Deadlock { blocked }));
+            }
+        }
+    }
+
+    fn turn(&mut self, index: usize) -> Result<(), SchedulerError> {
+        loop {
+            let task = &mut self.tasks[index];
+            let reason = task
+                .computer
+                .resume()
+                .map_err(|source| SchedulerError::Computer {
+                    computer: index,
+                    source,
+                })?;
+            match reason {
+                StopReason::ProducedOutput(value) => {
+                    self.history.push((index, value));
+                    match task.output_to {
+                        Some(to) => self.tasks[to].computer.push_input(value),
+                        None => task.output.push(value),
+                    }
+                }
+                StopReason::NeedsInput => return Ok(()),
+                StopReason::Halted => {
+                    task.halted = true;
+                    return Ok(());
+                }
+                StopReason::Breakpoint | StopReason::Watchpoint { .. } => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amplifiers(program: &[i64], phases: &[i64], feedback: bool) -> Scheduler {
+        let mut scheduler = Scheduler::new();
+        for &phase in phases {
+            let computer = scheduler.add(Computer::from_mem(program.to_vec()));
+            scheduler.push_input(computer, phase);
+            if computer > 0 {
+                scheduler.connect(computer - 1, computer);
+            }
+        }
+        if feedback {
+            scheduler.connect(phases.len() - 1, 0);
+        }
+        scheduler.push_input(0, 0);
+        scheduler
+    }
+
+    #[test]
+    fn feedback_loop() -> Result<(), SchedulerError> {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut scheduler = amplifiers(&program, &[9, 8, 7, 6, 5], true);
+        scheduler.run()?;
+        // The last signal is left waiting for the first amplifier, which has halted
+        assert_eq!(scheduler.history.last(), Some(&(4, 139629729)));
+        // Always in the same order
+        let mut again = amplifiers(&program, &[9, 8, 7, 6, 5], true);
+        again.run()?;
+        assert_eq!(scheduler.history, again.history);
+        assert_eq!(scheduler.history.len(), 25);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_deadlock() {
+        // Wants three inputs before it outputs anything, but only the first amplifier gets
+        // two to begin with
+        let program = [3, 20, 3, 20, 3, 20, 4, 20, 99];
+        let mut scheduler = amplifiers(&program, &[1, 2], true);
+        match scheduler.run() {
+            Err(SchedulerError::Deadlock(Deadlock { blocked })) => {
+                assert_eq!(blocked, vec![(0, 4), (1, 2)])
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Saving and restoring the state of a `Computer`, e.g. to continue a long running game
+//! later. Snapshots are stored as JSON.
+//!
+//! Only the machine itself is saved: memory, registers and queued input. The `IO` a
+//! computer is connected to (channels and the like) is not part of it, and neither are
+//! breakpoints or traces.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::memory::DEFAULT_LIMIT;
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub pc: usize,
+    pub relative_base: i64,
+    pub memory: Vec<i64>,
+    #[serde(default = "default_limit")]
+    pub memory_limit: usize,
+    /// Input queued with `Computer::push_input` that has not been consumed yet.
+    #[serde(default)]
+    pub input: Vec<i64>,
+    #[serde(default)]
+    pub steps: u64,
+}
+
+fn default_limit() -> usize {
+    DEFAULT_LIMIT
+}
+
+impl Snapshot {
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(reader: R) -> Result<Snapshot> {
+        let snapshot: Snapshot = serde_json::from_reader(reader)?;
+        if snapshot.memory.len() > snapshot.memory_limit {
+            bail!(
+                "Snapshot has {} words of memory, more than its limit of {}",
+                snapshot.memory.len(),
+                snapshot.memory_limit
+            );
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot> {
+        Snapshot::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, StopReason};
+
+    #[test]
+    fn resume_from_snapshot() -> Result<()> {
+        // Sum inputs until a 0 is read, then output the sum
+        let program = crate::asm::assemble(
+            "
+            loop:
+                INPUT &value
+                IF NOT &value JUMP TO done
+                ADD &sum &value => &sum
+                IF 1 JUMP TO loop
+            done:
+                OUTPUT &sum
+                HALT
+            value: DATA 0
+            sum: DATA 0
+            ",
+        )?;
+        let mut computer = Computer::from_mem(program);
+        computer.push_input(3);
+        computer.push_input(4);
+        assert_eq!(computer.resume()?, StopReason::NeedsInput);
+        computer.push_input(5);
+
+        let mut buffer = Vec::new();
+        computer.snapshot().write(&mut buffer)?;
+        let snapshot = Snapshot::read(&buffer[..])?;
+        assert_eq!(snapshot, computer.snapshot());
+
+        let mut restored = Computer::from_snapshot(snapshot);
+        assert_eq!(restored.pc(), computer.pc());
+        restored.push_input(0);
+        assert_eq!(restored.resume()?, StopReason::ProducedOutput(12));
+        assert_eq!(restored.resume()?, StopReason::Halted);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_snapshot() {
+        let json = r#"{"pc":0,"relative_base":0,"memory":[99,0,0],"memory_limit":2}"#;
+        assert!(Snapshot::read(json.as_bytes()).is_err());
+        assert!(Snapshot::read(&b"{\"pc\":0}"[..]).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Threaded code: an execution engine that translates each basic block of a program into a
+//! chain of Rust closures the first time it runs, so running it again skips decoding and
+//! all of the interpreter's bookkeeping.
+//!
+//! A block is a run of straight-line instructions ending with a jump. Input, output and
+//! halt end a block too, but are left to the interpreter, as is any code the program
+//! writes to. Once an address has been written, code covering it is never translated
+//! again, so self-modifying programs work the same as on the interpreter, just slower.
+
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use crate::computer::{Instruction, Param};
+use crate::error::IntcodeError;
+use crate::memory::Memory;
+use crate::overflow::Overflow;
+
+/// Which execution engine `Computer::run` and `Computer::resume` use. `Computer::step`
+/// always interprets a single instruction.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Backend {
+    Interpreter,
+    Threaded,
+}
+
+/// What an op did, besides changing memory or the relative base.
+pub(crate) enum Effect {
+    Next,
+    Wrote(usize),
+    Jump(usize),
+}
+
+type Run = dyn Fn(&mut Memory, &mut i64) -> Result<Effect, IntcodeError> + Send + Sync;
+
+pub(crate) struct Op {
+    pub pc: usize,
+    pub next: usize,
+    pub run: Box<Run>,
+}
+
+pub(crate) struct Block {
+    pub ops: Vec<Op>,
+    /// One past the last address of the block.
+    pub end: usize,
+}
+
+#[derive(Clone)]
+enum Slot {
+    Untranslated,
+    Interpret,
+    Block(Arc<Block>),
+}
+
+/// The translated blocks of a program, by start address.
+#[derive(Clone, Default)]
+pub(crate) struct ThreadedCode {
+    // Indexed by address. Code only runs where something has been loaded or written, so
+    // these never grow past the used memory.
+    blocks: Vec<Slot>,
+    // every address covered by a translated block
+    covered: Vec<bool>,
+    // addresses the program has written to that were, or could have been, code
+    modified: HashSet<usize>,
+}
+
+impl Debug for ThreadedCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThreadedCode")
+            .field(
+                "covered",
+                &self.covered.iter().filter(|&&covered| covered).count(),
+            )
+            .field("modified", &self.modified)
+            .finish()
+    }
+}
+
+impl ThreadedCode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The block starting at `pc`, translating it if this is the first time we get here.
+    /// `None` means the instruction at `pc` should be interpreted. Blocks are translated
+    /// for `overflow`, so the code has to be cleared when it changes.
+    pub fn block(&mut self, memory: &Memory, pc: usize, overflow: Overflow) -> Option<Arc<Block>> {
+        match self.blocks.get(pc) {
+            Some(Slot::Block(block)) => return Some(block.clone()),
+            Some(Slot::Interpret) => return None,
+            Some(Slot::Untranslated) => {}
+            // Past the end of memory, let the interpreter report it
+            None if pc >= memory.len() => return None,
+            None => self.blocks.resize(memory.len(), Slot::Untranslated),
+        }
+        let block = self.translate(memory, pc, overflow).map(Arc::new);
+        self.blocks[pc] = match &block {
+            Some(block) => {
+                if self.covered.len() < block.end {
+                    self.covered.resize(block.end, false);
+                }
+                self.covered[pc..block.end]
+                    .iter_mut()
+                    .for_each(|covered| *covered = true);
+                Slot::Block(block.clone())
+            }
+            None => Slot::Interpret,
+        };
+        block
+    }
+
+    /// Note a write to `addr`. Returns true if that changed translated code, which then
+    /// has to be interpreted from now on.
+    pub fn written(&mut self, addr: usize) -> bool {
+        if !self.covered.get(addr).copied().unwrap_or(false) {
+            return false;
+        }
+        self.modified.insert(addr);
+        // Rare enough that it is fine to start over
+        self.blocks.clear();
+        self.covered.clear();
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.covered.clear();
+    }
+
+    fn translate(&self, memory: &Memory, start: usize, overflow: Overflow) -> Option<Block> {
+        let mut ops = Vec::new();
+        let mut pc = start;
+        while let Some(instr) = decode(memory, pc) {
+            let next = pc + instr.len();
+            if (pc..next).any(|addr| self.modified.contains(&addr)) {
+                break;
+            }
+            let run = match compile(pc, memory.get(pc)?, instr, overflow) {
+                Some(run) => run,
+                None => break,
+            };
+            ops.push(Op { pc, next, run });
+            pc = next;
+            if let Instruction::JumpIfTrue { .. } | Instruction::JumpIfFalse { .. } = instr {
+                break;
+            }
+        }
+        if ops.is_empty() {
+            None
+        } else {
+            Some(Block { ops, end: pc })
+        }
+    }
+}
+
+fn decode(memory: &Memory, pc: usize) -> Option<Instruction> {
+    let mut words = Vec::with_capacity(4);
+    for addr in pc..pc + 4 {
+        match memory.get(addr) {
+            Some(word) => words.push(word),
+            None => break,
+        }
+    }
+    Instruction::decode(&words, 0).ok()
+}
+
+// Turn one instruction into a closure, or `None` for instructions the interpreter handles
+fn compile(pc: usize, opcode: i64, instr: Instruction, overflow: Overflow) -> Option<Box<Run>> {
+    use Instruction::*;
+    Some(match instr {
+        Add {
+            a,
+            b,
+            result_location,
+        } => binary(pc, opcode, a, b, result_location, move |a, b| {
+            overflow
+                .add(a, b)
+                .map_err(|value| overflow.error(pc, opcode, value))
+        }),
+        Multiply {
+            a,
+            b,
+            result_location,
+        } => binary(pc, opcode, a, b, result_location, move |a, b| {
+            overflow
+                .mul(a, b)
+                .map_err(|value| overflow.error(pc, opcode, value))
+        }),
+        LessThan {
+            a,
+            b,
+            result_location,
+        } => binary(pc, opcode, a, b, result_location, |a, b| Ok((a < b) as i64)),
+        Equals {
+            a,
+            b,
+            result_location,
+        } => binary(
+            pc,
+            opcode,
+            a,
+            b,
+            result_location,
+            |a, b| Ok((a == b) as i64),
+        ),
+        AdjustRelativeBase { value } => Box::new(move |memory, base| {
+            *base += read(memory, *base, value, pc, opcode)?;
+            Ok(Effect::Next)
+        }),
+        JumpIfTrue { check, jump_to } => jump(pc, opcode, check, jump_to, |value| value > 0),
+        JumpIfFalse { check, jump_to } => jump(pc, opcode, check, jump_to, |value| value == 0),
+        Input { .. } | Output { .. } | Halt => return None,
+    })
+}
+
+// `f` fails when the result overflows
+fn binary<F>(pc: usize, opcode: i64, a: Param, b: Param, out: Param, f: F) -> Box<Run>
+where
+    F: Fn(i64, i64) -> Result<i64, IntcodeError> + Send + Sync + 'static,
+{
+    Box::new(move |memory, base| {
+        let value = f(
+            read(memory, *base, a, pc, opcode)?,
+            read(memory, *base, b, pc, opcode)?,
+        )?;
+        Ok(Effect::Wrote(write(memory, *base, out, value, pc, opcode)?))
+    })
+}
+
+fn jump<F>(pc: usize, opcode: i64, check: Param, jump_to: Param, taken: F) -> Box<Run>
+where
+    F: Fn(i64) -> bool + Send + Sync + 'static,
+{
+    Box::new(move |memory, base| {
+        if taken(read(memory, *base, check, pc, opcode)?) {
+            Ok(Effect::Jump(
+                read(memory, *base, jump_to, pc, opcode)? as usize
+            ))
+        } else {
+            Ok(Effect::Next)
+        }
+    })
+}
+
+fn read(
+    memory: &Memory,
+    base: i64,
+    param: Param,
+    pc: usize,
+    opcode: i64,
+) -> Result<i64, IntcodeError> {
+    let addr = match param {
+        Param::Immediate(value) => return Ok(value),
+        Param::Pos(addr) => addr,
+        Param::Relative(offset) => (base + offset) as usize,
+    };
+    memory.get(addr).ok_or(IntcodeError::InvalidRead {
+        pc,
+        opcode,
+        address: addr as i64,
+    })
+}
+
+fn write(
+    memory: &mut Memory,
+    base: i64,
+    param: Param,
+    value: i64,
+    pc: usize,
+    opcode: i64,
+) -> Result<usize, IntcodeError> {
+    let addr = match param {
+        Param::Immediate(_) => return Err(IntcodeError::WriteToImmediate { pc, opcode }),
+        Param::Pos(addr) => addr,
+        Param::Relative(offset) => (base + offset) as usize,
+    };
+    *memory.get_mut(addr).ok_or(IntcodeError::InvalidWrite {
+        pc,
+        opcode,
+        address: addr as i64,
+    })? = value;
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, StopReason};
+
+    fn run(program: &[i64], input: i64, backend: Backend) -> Result<Vec<i64>, IntcodeError> {
+        let mut computer = Computer::from_mem(program.to_vec());
+        computer.set_backend(backend);
+        computer.push_input(input);
+        let mut output = Vec::new();
+        loop {
+            match computer.resume()? {
+                StopReason::ProducedOutput(value) => output.push(value),
+                StopReason::Halted => return Ok(output),
+                reason => panic!("Unexpected stop: {:?}", reason),
+            }
+        }
+    }
+
+    #[test]
+    fn day9_answers() -> Result<(), IntcodeError> {
+        let program = crate::parse_program(include_str!("../../aoc09/input/input.txt")).unwrap();
+        assert_eq!(run(&program, 1, Backend::Threaded)?, vec![2932210790]);
+        assert_eq!(run(&program, 2, Backend::Threaded)?, vec![73144]);
+        Ok(())
+    }
+
+    #[test]
+    fn self_modifying_code() -> Result<(), IntcodeError> {
+        // Sum count + ... + 1 by patching the immediate of the ADD at 4, inside the block
+        let program = crate::asm::assemble(
+            "
+            start:
+                ADD &count 0 => &6
+                ADD &sum 0 => &sum
+                ADD &count -1 => &count
+                IF &count JUMP TO start
+                OUTPUT &sum
+                HALT
+            count: DATA 3
+            sum: DATA 0
+            ",
+        )
+        .unwrap();
+        assert_eq!(run(&program, 0, Backend::Interpreter)?, vec![6]);
+        assert_eq!(run(&program, 0, Backend::Threaded)?, vec![6]);
+        Ok(())
+    }
+
+    #[test]
+    fn input_patches_code() -> Result<(), IntcodeError> {
+        // INPUT writes the second operand of the ADD, which has been translated by then
+        let program = [3, 4, 1101, 0, 0, 20, 4, 20, 1105, 1, 0];
+        let run = |backend| {
+            let mut computer = Computer::from_mem(program.to_vec());
+            computer.set_backend(backend);
+            computer.push_input(5);
+            computer.push_input(7);
+            computer.push_input(9);
+            computer.run_to_input()
+        };
+        let (interpreted, _) = run(Backend::Interpreter)?;
+        assert_eq!(interpreted, vec![5, 7, 9]);
+        assert_eq!(run(Backend::Threaded)?.0, interpreted);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_point_at_instruction() {
+        // Three fine instructions, then a write to an immediate
+        let program = [1101, 1, 1, 20, 1101, 2, 2, 21, 109, 1, 11101, 3, 3, 4, 99];
+        let mut computer = Computer::from_mem(program.to_vec());
+        computer.set_backend(Backend::Threaded);
+        match computer.resume() {
+            Err(IntcodeError::WriteToImmediate {
+                pc: 10,
+                opcode: 11101,
+            }) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert_eq!(computer.pc(), 10);
+        assert_eq!(computer.relative_base(), 1);
+    }
+}
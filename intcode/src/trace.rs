@@ -0,0 +1,333 @@
+//! Execution traces. A trace is stored as JSON lines, one `TraceEvent` per executed
+//! instruction, e.g.
+//!
+//! ```text
+//! {"step":0,"pc":0,"base":0,"opcode":3,"instr":"INPUT &&9","writes":[[9,8]],"input":8}
+//! {"step":1,"pc":2,"base":0,"opcode":8,"instr":"IF &9 == &10 => &9","operands":[8,8],"writes":[[9,1]]}
+//! ```
+//!
+//! The format only depends on what the program does, so other intcode implementations can
+//! write it too, with a `Tracer`, and be diffed against this one with `replay`.
+
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::computer::{Instruction, Param};
+use crate::{Computer, IntcodeError, StopReason};
+
+/// One executed instruction.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    pub step: u64,
+    pub pc: usize,
+    /// The relative base before the instruction.
+    pub base: i64,
+    pub opcode: i64,
+    /// The decoded instruction, as printed by its `Display`.
+    pub instr: String,
+    /// The values of the parameters the instruction reads.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operands: Vec<i64>,
+    /// `(address, value)` of every memory write.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writes: Vec<(usize, i64)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> Result<()> {
+        for event in &self.events {
+            serde_json::to_writer(&mut writer, event)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_jsonl<R: BufRead>(reader: R) -> Result<Trace> {
+        let events = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|(index, line)| {
+                serde_json::from_str(&line?)
+                    .with_context(|| format!("Invalid trace event on line {}", index + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Trace { events })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_jsonl(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Trace> {
+        Trace::read_jsonl(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Records a `Trace` for another intcode implementation. Give it every instruction before
+/// it is executed, and then what the instruction did.
+#[derive(Debug, Clone, Default)]
+pub struct Tracer {
+    trace: Option<Trace>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start recording.
+    pub fn enable(&mut self) {
+        self.trace = Some(Trace::new());
+    }
+
+    /// Stop recording and return what has been recorded.
+    pub fn take(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Record the instruction at `pc`, if recording. `base` is the relative base, for
+    /// reading its operands.
+    pub fn instruction<T>(&mut self, memory: &[T], pc: usize, base: i64) -> Result<(), IntcodeError>
+    where
+        T: Copy + Into<i64>,
+    {
+        let trace = match &mut self.trace {
+            Some(trace) => trace,
+            None => return Ok(()),
+        };
+        let instr = Instruction::decode(memory, pc)?;
+        // Memory past the end reads as 0, like for `Computer`
+        let read = |addr: i64| match memory.get(addr as usize) {
+            Some(&word) if addr >= 0 => word.into(),
+            _ => 0,
+        };
+        let (reads, _) = instr.operands();
+        let operands = reads
+            .into_iter()
+            .map(|param| match param {
+                Param::Pos(addr) => read(addr as i64),
+                Param::Immediate(value) => value,
+                Param::Relative(offset) => read(base + offset),
+            })
+            .collect();
+        trace.events.push(TraceEvent {
+            step: trace.events.len() as u64,
+            pc,
+            base,
+            opcode: memory[pc].into(),
+            instr: instr.to_string(),
+            operands,
+            writes: Vec::new(),
+            input: None,
+            output: None,
+        });
+        Ok(())
+    }
+
+    /// Record that the instruction wrote `value` to `addr`.
+    pub fn write(&mut self, addr: usize, value: i64) {
+        if let Some(event) = self.event() {
+            event.writes.push((addr, value));
+        }
+    }
+
+    /// Record that the instruction got `value` as input.
+    pub fn input(&mut self, value: i64) {
+        if let Some(event) = self.event() {
+            event.input = Some(value);
+        }
+    }
+
+    /// Record that the instruction output `value`.
+    pub fn output(&mut self, value: i64) {
+        if let Some(event) = self.event() {
+            event.output = Some(value);
+        }
+    }
+
+    // The event of the instruction being executed
+    fn event(&mut self) -> Option<&mut TraceEvent> {
+        self.trace
+            .as_mut()
+            .and_then(|trace| trace.events.last_mut())
+    }
+}
+
+/// The first place two runs disagree. `None` means that run stopped before that step.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Divergence {
+    pub step: u64,
+    pub expected: Option<TraceEvent>,
+    pub actual: Option<TraceEvent>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let event = |event: &Option<TraceEvent>| match event {
+            Some(event) => serde_json::to_string(event).unwrap_or_default(),
+            None => "<stopped>".to_string(),
+        };
+        writeln!(f, "Runs diverge at step {}", self.step)?;
+        writeln!(f, "  expected: {}", event(&self.expected))?;
+        write!(f, "  actual:   {}", event(&self.actual))
+    }
+}
+
+/// Run `program` again, feeding it the inputs recorded in `trace`, and compare every step.
+/// Returns the first divergence, or `None` if the runs agree. A replay that fails with an
+/// error or wants more input than was recorded counts as stopping early, and so does a
+/// trace that ends while the replay still runs, like one cut short by a crash.
+pub fn replay(program: &[i64], trace: &Trace) -> Option<Divergence> {
+    let mut computer = Computer::from_mem(program.to_vec());
+    computer.enable_trace();
+
+    for expected in &trace.events {
+        if let Some(input) = expected.input {
+            computer.push_input(input);
+        }
+        let stop = computer.step();
+        let actual = computer.trace_mut().and_then(|trace| trace.events.pop());
+        if actual.as_ref() != Some(expected) {
+            return Some(Divergence {
+                step: expected.step,
+                expected: Some(expected.clone()),
+                actual,
+            });
+        }
+        if let Ok(Some(StopReason::Halted)) = stop {
+            return None;
+        }
+    }
+    computer.step().ok();
+    computer
+        .trace_mut()
+        .and_then(|trace| trace.events.pop())
+        .map(|actual| Divergence {
+            step: actual.step,
+            expected: None,
+            actual: Some(actual),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(program: &[i64], input: &[i64]) -> Result<Trace> {
+        let mut computer = Computer::from_mem(program.to_vec());
+        computer.enable_trace();
+        for &value in input {
+            computer.push_input(value);
+        }
+        while let StopReason::ProducedOutput(_) = computer.resume()? {}
+        Ok(computer.take_trace().unwrap())
+    }
+
+    #[test]
+    fn records_events() -> Result<()> {
+        let is_eight = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let trace = record(&is_eight, &[8])?;
+        assert_eq!(trace.events.len(), 4);
+        assert_eq!(
+            trace.events[0],
+            TraceEvent {
+                step: 0,
+                pc: 0,
+                base: 0,
+                opcode: 3,
+                instr: "INPUT &&9".to_string(),
+                operands: vec![],
+                writes: vec![(9, 8)],
+                input: Some(8),
+                output: None,
+            }
+        );
+        assert_eq!(trace.events[1].operands, vec![8, 8]);
+        assert_eq!(trace.events[1].writes, vec![(9, 1)]);
+        assert_eq!(trace.events[2].output, Some(1));
+        assert_eq!(trace.events[3].instr, "HALT");
+        Ok(())
+    }
+
+    #[test]
+    fn jsonl_round_trip() -> Result<()> {
+        let trace = record(&[109, 15, 203, 1, 4, 16, 99], &[42])?;
+        let mut buffer = Vec::new();
+        trace.write_jsonl(&mut buffer)?;
+        assert_eq!(Trace::read_jsonl(&buffer[..])?, trace);
+        Ok(())
+    }
+
+    #[test]
+    fn tracer() -> Result<()> {
+        // Echo the input through base + 1
+        let program = [109, 9, 203, 1, 204, 1, 99, 0, 0, 0, 0];
+        let mut tracer = Tracer::new();
+        tracer.enable();
+        tracer.instruction(&program, 0, 0)?;
+        tracer.instruction(&program, 2, 9)?;
+        tracer.write(10, 42);
+        tracer.input(42);
+        let mut memory = program.to_vec();
+        memory[10] = 42;
+        tracer.instruction(&memory, 4, 9)?;
+        tracer.output(42);
+        tracer.instruction(&memory, 6, 9)?;
+        assert_eq!(tracer.take(), Some(record(&program, &[42])?));
+        Ok(())
+    }
+
+    #[test]
+    fn replay_finds_divergence() -> Result<()> {
+        let is_eight = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let trace = record(&is_eight, &[8])?;
+        assert_eq!(replay(&is_eight, &trace), None);
+
+        let is_seven = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 7];
+        let divergence = replay(&is_seven, &trace).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.actual.unwrap().operands, vec![8, 7]);
+        Ok(())
+    }
+
+    #[test]
+    fn replay_truncated_trace() -> Result<()> {
+        let is_eight = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut trace = record(&is_eight, &[8])?;
+        let output = trace.events.remove(2);
+        trace.events.truncate(2);
+        assert_eq!(
+            replay(&is_eight, &trace),
+            Some(Divergence {
+                step: 2,
+                expected: None,
+                actual: Some(output),
+            })
+        );
+        // Stopped waiting for input, like the recorded run
+        assert_eq!(replay(&is_eight, &Trace::new()), None);
+        Ok(())
+    }
+}
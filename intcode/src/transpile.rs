@@ -0,0 +1,504 @@
+//! Translate an intcode program into Rust source, so it can be read and run natively.
+//!
+//! The program becomes an `async fn run<I: IO>(io: &mut I)` driving a state machine: each
+//! basic block found by the disassembler is an arm of a `match` on the pc, and jumps just
+//! set the pc. Code the disassembler can't find, like targets of jump tables, is run by a
+//! small interpreter included in the output, one instruction at a time until it gets back
+//! to the start of a block.
+//!
+//! Instructions the program writes to through a position parameter, typically to patch in
+//! an operand, are left to the interpreter too. Other writes to translated code can't be
+//! found before running it, so the generated code keeps track of them: a block that has
+//! been written to is interpreted from then on, and a block that writes to itself through
+//! a relative parameter stops right after the write.
+//!
+//! ADD and MULTIPLY go through `add` and `mul` functions in the output, which overflow
+//! the way the chosen `Overflow` says, like on the computer.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+
+use crate::cfg::{falls_through, leaders};
+use crate::computer::{Instruction, Param};
+use crate::disasm::{disassemble, is_unconditional};
+use crate::memory::DEFAULT_LIMIT;
+use crate::overflow::Overflow;
+
+pub fn transpile(program: &[i64]) -> Result<String> {
+    transpile_with_overflow(program, Overflow::default())
+}
+
+pub fn transpile_with_overflow(program: &[i64], overflow: Overflow) -> Result<String> {
+    let disasm = disassemble(program);
+    let written: BTreeSet<usize> = disasm
+        .code
+        .values()
+        .filter_map(|instr| match instr.operands() {
+            (_, Some(Param::Pos(addr))) => Some(addr),
+            _ => None,
+        })
+        .collect();
+    let (patched, code): (BTreeMap<_, _>, BTreeMap<_, _>) = disasm
+        .code
+        .iter()
+        .map(|(&pc, &instr)| (pc, instr))
+        .partition(|(pc, instr)| written.range(pc..&(pc + instr.len())).next().is_some());
+
+    let ranges = code_ranges(&code);
+    let leaders = leaders(&code, &disasm.labels);
+    let mut out = String::new();
+    writeln!(out, "{}", HEADER)?;
+    if !patched.is_empty() {
+        let pcs: Vec<String> = patched.keys().map(|pc| pc.to_string()).collect();
+        writeln!(
+            out,
+            "// Interpreted, since the program changes them: {}\n",
+            pcs.join(", ")
+        )?;
+    }
+    writeln!(
+        out,
+        "const PROGRAM: [i64; {}] = {:?};",
+        program.len(),
+        program
+    )?;
+    writeln!(out)?;
+    let ranges: Vec<String> = ranges
+        .iter()
+        .map(|(start, end)| format!("{}..={}", start, end - 1))
+        .collect();
+    if ranges.is_empty() {
+        writeln!(out, "fn is_code(_addr: i64) -> bool {{")?;
+        writeln!(out, "    false")?;
+    } else {
+        writeln!(out, "fn is_code(addr: i64) -> bool {{")?;
+        writeln!(out, "    matches!(addr, {})", ranges.join(" | "))?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "// The default memory limit of `intcode::Computer`")?;
+    writeln!(out, "const MEMORY_LIMIT: usize = {};", DEFAULT_LIMIT)?;
+    writeln!(out)?;
+    writeln!(out, "{}", MEMORY)?;
+    writeln!(out)?;
+    writeln!(out, "{}", arithmetic(overflow))?;
+    writeln!(out)?;
+    writeln!(out, "{}", INTERPRETER)?;
+    writeln!(out)?;
+    writeln!(out, "pub async fn run<I: IO>(io: &mut I) -> Result<()> {{")?;
+    writeln!(out, "    let mut mem = Memory::new();")?;
+    writeln!(out, "    let mut base: i64 = 0;")?;
+    writeln!(out, "    let mut pc: usize = 0;")?;
+    writeln!(out, "    loop {{")?;
+    writeln!(out, "        match pc {{")?;
+
+    for block in blocks(&code, &leaders) {
+        let (start, end) = match (block.first(), block.last()) {
+            (Some((start, _)), Some((pc, instr))) => (*start, pc + instr.len()),
+            _ => continue,
+        };
+        writeln!(
+            out,
+            "            {} if !mem.changed({}, {}) => {{",
+            start, start, end
+        )?;
+        for (index, (pc, instr)) in block.iter().enumerate() {
+            writeln!(out, "                // {}: {}", pc, instr)?;
+            for line in statements(*pc, instr)? {
+                writeln!(out, "                {}", line)?;
+            }
+            let next = pc + instr.len();
+            let last = index == block.len() - 1;
+            if let (_, Some(Param::Relative(_))) = instr.operands() {
+                if !last {
+                    writeln!(
+                        out,
+                        "                if mem.changed({}, {}) {{ pc = {}; continue; }}",
+                        start, end, next
+                    )?;
+                }
+            }
+            if last && falls_through(instr) {
+                writeln!(out, "                pc = {};", next)?;
+            }
+        }
+        writeln!(out, "            }}")?;
+    }
+
+    writeln!(
+        out,
+        "            _ => match interpret(&mut mem, &mut base, pc, io).await? {{"
+    )?;
+    writeln!(out, "                Some(next) => pc = next,")?;
+    writeln!(out, "                None => return Ok(()),")?;
+    writeln!(out, "            }},")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+const HEADER: &str = "\
+// Generated by intcode-to-rust
+
+#![allow(unreachable_code, unused_parens)]
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+use intcode::IO;
+";
+
+const MEMORY: &str = "\
+struct Memory {
+    words: Vec<i64>,
+    // addresses of translated code the program has written to
+    changed: BTreeSet<usize>,
+}
+
+impl Memory {
+    fn new() -> Memory {
+        Memory {
+            words: PROGRAM.to_vec(),
+            changed: BTreeSet::new(),
+        }
+    }
+
+    fn get(&self, addr: i64) -> Result<i64> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            bail!(\"Invalid read from {}\", addr);
+        }
+        Ok(self.words.get(addr as usize).copied().unwrap_or(0))
+    }
+
+    fn set(&mut self, addr: i64, value: i64) -> Result<()> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            bail!(\"Invalid write to {}\", addr);
+        }
+        let addr = addr as usize;
+        if is_code(addr as i64) {
+            self.changed.insert(addr);
+        }
+        if addr >= self.words.len() {
+            self.words.resize(addr + 1, 0);
+        }
+        self.words[addr] = value;
+        Ok(())
+    }
+
+    /// Whether the program has written to the translated code in `start..end`, which then
+    /// has to be interpreted instead.
+    fn changed(&self, start: usize, end: usize) -> bool {
+        !self.changed.is_empty() && self.changed.range(start..end).next().is_some()
+    }
+}";
+
+const INTERPRETER: &str = "\
+/// Run the instruction at `pc`, for code that was not translated. Returns the next pc, or
+/// `None` if the program halted.
+async fn interpret<I: IO>(
+    mem: &mut Memory,
+    base: &mut i64,
+    pc: usize,
+    io: &mut I,
+) -> Result<Option<usize>> {
+    let opcode = mem.get(pc as i64)?;
+    let mode = |n: usize| opcode / 10_i64.pow(n as u32 + 1) % 10;
+    let read = |n: usize| -> Result<i64> {
+        let value = mem.get((pc + n) as i64)?;
+        match mode(n) {
+            0 => mem.get(value),
+            1 => Ok(value),
+            2 => mem.get(*base + value),
+            mode => bail!(\"Unknown mode {} at {}\", mode, pc),
+        }
+    };
+    let addr = |n: usize| -> Result<i64> {
+        let value = mem.get((pc + n) as i64)?;
+        match mode(n) {
+            0 => Ok(value),
+            2 => Ok(*base + value),
+            mode => bail!(\"Invalid mode {} for a write at {}\", mode, pc),
+        }
+    };
+    Ok(Some(match opcode % 100 {
+        1 | 2 | 7 | 8 => {
+            let (a, b) = (read(1)?, read(2)?);
+            let value = match opcode % 100 {
+                1 => add(a, b, pc)?,
+                2 => mul(a, b, pc)?,
+                7 => (a < b) as i64,
+                _ => (a == b) as i64,
+            };
+            let out = addr(3)?;
+            mem.set(out, value)?;
+            pc + 4
+        }
+        3 => {
+            let out = addr(1)?;
+            let value = io.input().await?;
+            mem.set(out, value)?;
+            pc + 2
+        }
+        4 => {
+            io.output(read(1)?).await?;
+            pc + 2
+        }
+        5 if read(1)? > 0 => read(2)? as usize,
+        6 if read(1)? == 0 => read(2)? as usize,
+        5 | 6 => pc + 3,
+        9 => {
+            let value = read(1)?;
+            *base += value;
+            pc + 2
+        }
+        99 => return Ok(None),
+        _ => bail!(\"Unknown opcode {} at {}\", opcode, pc),
+    }))
+}";
+
+// The `add` and `mul` functions of the output
+fn arithmetic(overflow: Overflow) -> String {
+    let functions: Vec<String> = [("add", "ADD", "+"), ("mul", "MULTIPLY", "*")]
+        .iter()
+        .map(|(name, instr, op)| match overflow {
+            Overflow::Checked => format!(
+                "\
+fn {0}(a: i64, b: i64, pc: usize) -> Result<i64> {{
+    match a.checked_{0}(b) {{
+        Some(value) => Ok(value),
+        None => bail!(\"Overflow in {1} at {{}}\", pc),
+    }}
+}}",
+                name, instr
+            ),
+            Overflow::Wide => format!(
+                "\
+fn {0}(a: i64, b: i64, pc: usize) -> Result<i64> {{
+    let value = a as i128 {2} b as i128;
+    if value < i64::MIN as i128 || value > i64::MAX as i128 {{
+        bail!(\"Cannot store {{}}, result of {1} at {{}}\", value, pc);
+    }}
+    Ok(value as i64)
+}}",
+                name, instr, op
+            ),
+            Overflow::Wrapping | Overflow::Saturating => format!(
+                "\
+fn {0}(a: i64, b: i64, _pc: usize) -> Result<i64> {{
+    Ok(a.{1}_{0}(b))
+}}",
+                name, overflow
+            ),
+        })
+        .collect();
+    functions.join("\n\n")
+}
+
+// Split the code into basic blocks: runs of consecutive instructions, each starting at a
+// leader
+fn blocks(
+    code: &BTreeMap<usize, Instruction>,
+    leaders: &BTreeSet<usize>,
+) -> Vec<Vec<(usize, Instruction)>> {
+    let mut blocks: Vec<Vec<(usize, Instruction)>> = Vec::new();
+    let mut next = None;
+    for (&pc, &instr) in code {
+        match blocks.last_mut() {
+            Some(block) if next == Some(pc) && !leaders.contains(&pc) => block.push((pc, instr)),
+            _ => blocks.push(vec![(pc, instr)]),
+        }
+        next = if falls_through(&instr) {
+            Some(pc + instr.len())
+        } else {
+            None
+        };
+    }
+    blocks
+}
+
+// Contiguous runs of addresses covered by instructions, as (start, end)
+fn code_ranges(code: &BTreeMap<usize, Instruction>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (&pc, instr) in code {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == pc => *end = pc + instr.len(),
+            _ => ranges.push((pc, pc + instr.len())),
+        }
+    }
+    ranges
+}
+
+fn read(param: &Param) -> String {
+    match param {
+        Param::Pos(addr) => format!("mem.get({})?", addr),
+        Param::Immediate(value) if *value < 0 => format!("({})", value),
+        Param::Immediate(value) => value.to_string(),
+        Param::Relative(0) => "mem.get(base)?".to_string(),
+        Param::Relative(offset) => format!("mem.get(base + {})?", offset),
+    }
+}
+
+fn write(pc: usize, param: &Param, value: &str) -> Result<String> {
+    let addr = match param {
+        Param::Pos(addr) => addr.to_string(),
+        Param::Immediate(_) => bail!("Instruction at {} writes to an immediate", pc),
+        Param::Relative(0) => "base".to_string(),
+        Param::Relative(offset) => format!("base + {}", offset),
+    };
+    Ok(format!("mem.set({}, {})?;", addr, value))
+}
+
+fn jump(target: &Param) -> Vec<String> {
+    match target {
+        Param::Immediate(target) if *target >= 0 => {
+            vec![format!("pc = {};", target), "continue;".to_string()]
+        }
+        target => vec![
+            format!("pc = {} as usize;", read(target)),
+            "continue;".to_string(),
+        ],
+    }
+}
+
+fn statements(pc: usize, instr: &Instruction) -> Result<Vec<String>> {
+    use Instruction::*;
+    Ok(match instr {
+        Add {
+            a,
+            b,
+            result_location,
+        } => vec![write(
+            pc,
+            result_location,
+            &format!("add({}, {}, {})?", read(a), read(b), pc),
+        )?],
+        Multiply {
+            a,
+            b,
+            result_location,
+        } => vec![write(
+            pc,
+            result_location,
+            &format!("mul({}, {}, {})?", read(a), read(b), pc),
+        )?],
+        LessThan {
+            a,
+            b,
+            result_location,
+        } => vec![write(
+            pc,
+            result_location,
+            &format!("({} < {}) as i64", read(a), read(b)),
+        )?],
+        Equals {
+            a,
+            b,
+            result_location,
+        } => vec![write(
+            pc,
+            result_location,
+            &format!("({} == {}) as i64", read(a), read(b)),
+        )?],
+        Input { result_location } => vec![write(pc, result_location, "io.input().await?")?],
+        Output { param } => vec![format!("io.output({}).await?;", read(param))],
+        AdjustRelativeBase { value } => vec![format!("base += {};", read(value))],
+        JumpIfTrue { .. } | JumpIfFalse { .. } if is_unconditional(instr) => {
+            let (reads, _) = instr.operands();
+            jump(&reads[1])
+        }
+        JumpIfTrue { check, jump_to } => {
+            indent_if(format!("if {} > 0 {{", read(check)), jump(jump_to))
+        }
+        JumpIfFalse { check, jump_to } => {
+            indent_if(format!("if {} == 0 {{", read(check)), jump(jump_to))
+        }
+        Halt => vec!["return Ok(());".to_string()],
+    })
+}
+
+fn indent_if(condition: String, body: Vec<String>) -> Vec<String> {
+    let mut lines = vec![condition];
+    lines.extend(body.into_iter().map(|line| format!("    {}", line)));
+    lines.push("}".to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_blocks() -> Result<()> {
+        // Count &20 up to 5 and output it
+        let program = [1001, 20, 1, 20, 1007, 20, 5, 21, 1005, 21, 0, 4, 20, 99];
+        let source = transpile(&program)?;
+        assert!(source.contains(
+            "            0 if !mem.changed(0, 11) => {
+                // 0: ADD &20 1 => &&20
+                mem.set(20, add(mem.get(20)?, 1, 0)?)?;
+                // 4: IF &20 < 5 => &21
+                mem.set(21, (mem.get(20)? < 5) as i64)?;
+                // 8: IF &21 JUMP TO 0
+                if mem.get(21)? > 0 {
+                    pc = 0;
+                    continue;
+                }
+                pc = 11;
+            }
+            11 if !mem.changed(11, 14) => {
+                // 11: OUTPUT &20
+                io.output(mem.get(20)?).await?;
+                // 13: HALT
+                return Ok(());
+            }"
+        ));
+        assert!(source.contains("matches!(addr, 0..=13)"));
+        Ok(())
+    }
+
+    #[test]
+    fn relative_base() -> Result<()> {
+        let source = transpile(&[109, 15, 203, -1, 204, -1, 99])?;
+        assert!(source.contains("base += 15;"));
+        assert!(source.contains(
+            "                mem.set(base + -1, io.input().await?)?;
+                if mem.changed(0, 7) { pc = 4; continue; }"
+        ));
+        assert!(source.contains("io.output(mem.get(base + -1)?).await?;"));
+        Ok(())
+    }
+
+    #[test]
+    fn interprets_patched_code() -> Result<()> {
+        // The ADD changes its own first parameter
+        let source = transpile(&[1001, 1, 1, 1, 99])?;
+        assert!(source.contains("// Interpreted, since the program changes them: 0"));
+        assert!(!source.contains("            0 if"));
+        assert!(source.contains("            4 if !mem.changed(4, 5) => {"));
+
+        // Nothing is left to translate
+        let source = transpile(&[1101, 1, 1, 0])?;
+        assert!(source.contains("fn is_code(_addr: i64) -> bool {\n    false\n}"));
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_policy() -> Result<()> {
+        let program = [1002, 20, 3, 20, 99];
+        let source = transpile_with_overflow(&program, Overflow::Checked)?;
+        assert!(source.contains("mem.set(20, mul(mem.get(20)?, 3, 0)?)?;"));
+        assert!(source.contains("match a.checked_mul(b) {"));
+        assert!(source.contains("None => bail!(\"Overflow in MULTIPLY at {}\", pc),"));
+
+        let source = transpile_with_overflow(&program, Overflow::Saturating)?;
+        assert!(source.contains("Ok(a.saturating_add(b))"));
+
+        let source = transpile_with_overflow(&program, Overflow::Wide)?;
+        assert!(source.contains("let value = a as i128 * b as i128;"));
+        assert!(source.contains("bail!(\"Cannot store {}, result of MULTIPLY at {}\", value, pc);"));
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// Generated by intcode-to-rust
+
+#![allow(unreachable_code, unused_parens)]
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+use intcode::IO;
+
+const PROGRAM: [i64; 678] = [3, 225, 1, 225, 6, 6, 1100, 1, 238, 225, 104, 0, 101, 71, 150, 224, 101, -123, 224, 224, 4, 224, 102, 8, 223, 223, 101, 2, 224, 224, 1, 224, 223, 223, 2, 205, 209, 224, 1001, 224, -3403, 224, 4, 224, 1002, 223, 8, 223, 101, 1, 224, 224, 1, 223, 224, 223, 1101, 55, 24, 224, 1001, 224, -79, 224, 4, 224, 1002, 223, 8, 223, 101, 1, 224, 224, 1, 223, 224, 223, 1, 153, 218, 224, 1001, 224, -109, 224, 4, 224, 1002, 223, 8, 223, 101, 5, 224, 224, 1, 224, 223, 223, 1002, 201, 72, 224, 1001, 224, -2088, 224, 4, 224, 102, 8, 223, 223, 101, 3, 224, 224, 1, 223, 224, 223, 1102, 70, 29, 225, 102, 5, 214, 224, 101, -250, 224, 224, 4, 224, 1002, 223, 8, 223, 1001, 224, 3, 224, 1, 223, 224, 223, 1101, 12, 52, 225, 1101, 60, 71, 225, 1001, 123, 41, 224, 1001, 224, -111, 224, 4, 224, 102, 8, 223, 223, 1001, 224, 2, 224, 1, 223, 224, 223, 1102, 78, 66, 224, 1001, 224, -5148, 224, 4, 224, 1002, 223, 8, 223, 1001, 224, 2, 224, 1, 223, 224, 223, 1101, 29, 77, 225, 1102, 41, 67, 225, 1102, 83, 32, 225, 1101, 93, 50, 225, 1102, 53, 49, 225, 4, 223, 99, 0, 0, 0, 677, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1105, 0, 99999, 1105, 227, 247, 1105, 1, 99999, 1005, 227, 99999, 1005, 0, 256, 1105, 1, 99999, 1106, 227, 99999, 1106, 0, 265, 1105, 1, 99999, 1006, 0, 99999, 1006, 227, 274, 1105, 1, 99999, 1105, 1, 280, 1105, 1, 99999, 1, 225, 225, 225, 1101, 294, 0, 0, 105, 1, 0, 1105, 1, 99999, 1106, 0, 300, 1105, 1, 99999, 1, 225, 225, 225, 1101, 314, 0, 0, 106, 0, 0, 1105, 1, 99999, 1107, 677, 677, 224, 1002, 223, 2, 223, 1005, 224, 329, 101, 1, 223, 223, 7, 677, 677, 224, 1002, 223, 2, 223, 1005, 224, 344, 1001, 223, 1, 223, 7, 226, 677, 224, 102, 2, 223, 223, 1006, 224, 359, 101, 1, 223, 223, 1108, 226, 226, 224, 1002, 223, 2, 223, 1005, 224, 374, 1001, 223, 1, 223, 8, 226, 677, 224, 1002, 223, 2, 223, 1006, 224, 389, 1001, 223, 1, 223, 1108, 226, 677, 224, 1002, 223, 2, 223, 1006, 224, 404, 101, 1, 223, 223, 1107, 677, 226, 224, 102, 2, 223, 223, 1006, 224, 419, 101, 1, 223, 223, 1007, 677, 677, 224, 1002, 223, 2, 223, 1005, 224, 434, 101, 1, 223, 223, 7, 677, 226, 224, 102, 2, 223, 223, 1006, 224, 449, 1001, 223, 1, 223, 1008, 226, 677, 224, 1002, 223, 2, 223, 1006, 224, 464, 101, 1, 223, 223, 8, 677, 677, 224, 1002, 223, 2, 223, 1006, 224, 479, 101, 1, 223, 223, 108, 226, 226, 224, 102, 2, 223, 223, 1005, 224, 494, 101, 1, 223, 223, 1107, 226, 677, 224, 1002, 223, 2, 223, 1006, 224, 509, 101, 1, 223, 223, 107, 226, 226, 224, 1002, 223, 2, 223, 1006, 224, 524, 1001, 223, 1, 223, 107, 677, 677, 224, 1002, 223, 2, 223, 1005, 224, 539, 101, 1, 223, 223, 1007, 226, 226, 224, 102, 2, 223, 223, 1006, 224, 554, 101, 1, 223, 223, 108, 677, 677, 224, 102, 2, 223, 223, 1005, 224, 569, 101, 1, 223, 223, 107, 677, 226, 224, 102, 2, 223, 223, 1005, 224, 584, 101, 1, 223, 223, 1008, 226, 226, 224, 102, 2, 223, 223, 1006, 224, 599, 101, 1, 223, 223, 1108, 677, 226, 224, 1002, 223, 2, 223, 1006, 224, 614, 101, 1, 223, 223, 8, 677, 226, 224, 102, 2, 223, 223, 1005, 224, 629, 1001, 223, 1, 223, 1008, 677, 677, 224, 102, 2, 223, 223, 1006, 224, 644, 101, 1, 223, 223, 1007, 226, 677, 224, 102, 2, 223, 223, 1005, 224, 659, 101, 1, 223, 223, 108, 226, 677, 224, 102, 2, 223, 223, 1006, 224, 674, 101, 1, 223, 223, 4, 223, 99, 226];
+
+fn is_code(addr: i64) -> bool {
+    matches!(addr, 0..=5)
+}
+
+// The default memory limit of `intcode::Computer`
+const MEMORY_LIMIT: usize = 16777216;
+
+struct Memory {
+    words: Vec<i64>,
+    // addresses of translated code the program has written to
+    changed: BTreeSet<usize>,
+}
+
+impl Memory {
+    fn new() -> Memory {
+        Memory {
+            words: PROGRAM.to_vec(),
+            changed: BTreeSet::new(),
+        }
+    }
+
+    fn get(&self, addr: i64) -> Result<i64> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            bail!("Invalid read from {}", addr);
+        }
+        Ok(self.words.get(addr as usize).copied().unwrap_or(0))
+    }
+
+    fn set(&mut self, addr: i64, value: i64) -> Result<()> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            bail!("Invalid write to {}", addr);
+        }
+        let addr = addr as usize;
+        if is_code(addr as i64) {
+            self.changed.insert(addr);
+        }
+        if addr >= self.words.len() {
+            self.words.resize(addr + 1, 0);
+        }
+        self.words[addr] = value;
+        Ok(())
+    }
+
+    /// Whether the program has written to the translated code in `start..end`, which then
+    /// has to be interpreted instead.
+    fn changed(&self, start: usize, end: usize) -> bool {
+        !self.changed.is_empty() && self.changed.range(start..end).next().is_some()
+    }
+}
+
+fn add(a: i64, b: i64, _pc: usize) -> Result<i64> {
+    Ok(a.wrapping_add(b))
+}
+
+fn mul(a: i64, b: i64, _pc: usize) -> Result<i64> {
+    Ok(a.wrapping_mul(b))
+}
+
+/// Run the instruction at `pc`, for code that was not translated. Returns the next pc, or
+/// `None` if the program halted.
+async fn interpret<I: IO>(
+    mem: &mut Memory,
+    base: &mut i64,
+    pc: usize,
+    io: &mut I,
+) -> Result<Option<usize>> {
+    let opcode = mem.get(pc as i64)?;
+    let mode = |n: usize| opcode / 10_i64.pow(n as u32 + 1) % 10;
+    let read = |n: usize| -> Result<i64> {
+        let value = mem.get((pc + n) as i64)?;
+        match mode(n) {
+            0 => mem.get(value),
+            1 => Ok(value),
+            2 => mem.get(*base + value),
+            mode => bail!("Unknown mode {} at {}", mode, pc),
+        }
+    };
+    let addr = |n: usize| -> Result<i64> {
+        let value = mem.get((pc + n) as i64)?;
+        match mode(n) {
+            0 => Ok(value),
+            2 => Ok(*base + value),
+            mode => bail!("Invalid mode {} for a write at {}", mode, pc),
+        }
+    };
+    Ok(Some(match opcode % 100 {
+        1 | 2 | 7 | 8 => {
+            let (a, b) = (read(1)?, read(2)?);
+            let value = match opcode % 100 {
+                1 => add(a, b, pc)?,
+                2 => mul(a, b, pc)?,
+                7 => (a < b) as i64,
+                _ => (a == b) as i64,
+            };
+            let out = addr(3)?;
+            mem.set(out, value)?;
+            pc + 4
+        }
+        3 => {
+            let out = addr(1)?;
+            let value = io.input().await?;
+            mem.set(out, value)?;
+            pc + 2
+        }
+        4 => {
+            io.output(read(1)?).await?;
+            pc + 2
+        }
+        5 if read(1)? > 0 => read(2)? as usize,
+        6 if read(1)? == 0 => read(2)? as usize,
+        5 | 6 => pc + 3,
+        9 => {
+            let value = read(1)?;
+            *base += value;
+            pc + 2
+        }
+        99 => return Ok(None),
+        _ => bail!("Unknown opcode {} at {}", opcode, pc),
+    }))
+}
+
+pub async fn run<I: IO>(io: &mut I) -> Result<()> {
+    let mut mem = Memory::new();
+    let mut base: i64 = 0;
+    let mut pc: usize = 0;
+    loop {
+        match pc {
+            0 if !mem.changed(0, 6) => {
+                // 0: INPUT &&225
+                mem.set(225, io.input().await?)?;
+                // 2: ADD &225 &6 => &&6
+                mem.set(6, add(mem.get(225)?, mem.get(6)?, 2)?)?;
+                pc = 6;
+            }
+            _ => match interpret(&mut mem, &mut base, pc, io).await? {
+                Some(next) => pc = next,
+                None => return Ok(()),
+            },
+        }
+    }
+}
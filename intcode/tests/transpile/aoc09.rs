@@ -0,0 +1,444 @@
+// Generated by intcode-to-rust
+
+#![allow(unreachable_code, unused_parens)]
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+use intcode::IO;
+
+const PROGRAM: [i64; 973] = [1102, 34463338, 34463338, 63, 1007, 63, 34463338, 63, 1005, 63, 53, 1101, 3, 0, 1000, 109, 988, 209, 12, 9, 1000, 209, 6, 209, 3, 203, 0, 1008, 1000, 1, 63, 1005, 63, 65, 1008, 1000, 2, 63, 1005, 63, 904, 1008, 1000, 0, 63, 1005, 63, 58, 4, 25, 104, 0, 99, 4, 0, 104, 0, 99, 4, 17, 104, 0, 99, 0, 0, 1101, 35, 0, 1007, 1102, 30, 1, 1013, 1102, 37, 1, 1017, 1101, 23, 0, 1006, 1101, 0, 32, 1008, 1102, 1, 29, 1000, 1101, 0, 38, 1010, 1101, 0, 24, 1002, 1101, 33, 0, 1003, 1101, 1, 0, 1021, 1102, 31, 1, 1019, 1101, 27, 0, 1014, 1102, 20, 1, 1005, 1101, 0, 0, 1020, 1102, 1, 892, 1027, 1101, 895, 0, 1026, 1102, 39, 1, 1015, 1102, 1, 370, 1029, 1102, 1, 28, 1001, 1102, 34, 1, 1012, 1101, 25, 0, 1016, 1101, 0, 375, 1028, 1101, 36, 0, 1018, 1101, 0, 21, 1004, 1102, 1, 26, 1009, 1101, 0, 249, 1022, 1101, 0, 660, 1025, 1101, 0, 665, 1024, 1102, 1, 22, 1011, 1102, 242, 1, 1023, 109, 5, 2102, 1, 3, 63, 1008, 63, 31, 63, 1005, 63, 205, 1001, 64, 1, 64, 1105, 1, 207, 4, 187, 1002, 64, 2, 64, 109, 8, 21102, 40, 1, 5, 1008, 1018, 37, 63, 1005, 63, 227, 1105, 1, 233, 4, 213, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 7, 2105, 1, 3, 1001, 64, 1, 64, 1106, 0, 251, 4, 239, 1002, 64, 2, 64, 109, -7, 1201, -7, 0, 63, 1008, 63, 20, 63, 1005, 63, 271, 1106, 0, 277, 4, 257, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -10, 1208, 0, 33, 63, 1005, 63, 295, 4, 283, 1106, 0, 299, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -6, 1207, 4, 27, 63, 1005, 63, 319, 1001, 64, 1, 64, 1105, 1, 321, 4, 305, 1002, 64, 2, 64, 109, 12, 1207, -1, 33, 63, 1005, 63, 339, 4, 327, 1105, 1, 343, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 6, 1206, 6, 355, 1106, 0, 361, 4, 349, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 21, 2106, 0, -8, 4, 367, 1106, 0, 379, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -29, 1202, 0, 1, 63, 1008, 63, 36, 63, 1005, 63, 403, 1001, 64, 1, 64, 1105, 1, 405, 4, 385, 1002, 64, 2, 64, 109, 11, 21107, 41, 40, -6, 1005, 1012, 421, 1105, 1, 427, 4, 411, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -11, 2101, 0, -4, 63, 1008, 63, 33, 63, 1005, 63, 453, 4, 433, 1001, 64, 1, 64, 1106, 0, 453, 1002, 64, 2, 64, 109, -7, 21108, 42, 40, 10, 1005, 1010, 469, 1105, 1, 475, 4, 459, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 1, 1201, 4, 0, 63, 1008, 63, 20, 63, 1005, 63, 497, 4, 481, 1105, 1, 501, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 5, 21107, 43, 44, 5, 1005, 1011, 523, 4, 507, 1001, 64, 1, 64, 1106, 0, 523, 1002, 64, 2, 64, 109, 20, 21108, 44, 44, -7, 1005, 1019, 541, 4, 529, 1106, 0, 545, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 2, 1205, -8, 561, 1001, 64, 1, 64, 1106, 0, 563, 4, 551, 1002, 64, 2, 64, 109, -23, 2108, 22, 0, 63, 1005, 63, 583, 1001, 64, 1, 64, 1105, 1, 585, 4, 569, 1002, 64, 2, 64, 109, -6, 2107, 30, 1, 63, 1005, 63, 605, 1001, 64, 1, 64, 1105, 1, 607, 4, 591, 1002, 64, 2, 64, 109, 23, 1205, -1, 621, 4, 613, 1105, 1, 625, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -19, 2102, 1, -3, 63, 1008, 63, 29, 63, 1005, 63, 647, 4, 631, 1106, 0, 651, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 28, 2105, 1, -7, 4, 657, 1106, 0, 669, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -17, 1206, 6, 687, 4, 675, 1001, 64, 1, 64, 1105, 1, 687, 1002, 64, 2, 64, 109, 2, 21101, 45, 0, 1, 1008, 1017, 42, 63, 1005, 63, 707, 1106, 0, 713, 4, 693, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -6, 2101, 0, -3, 63, 1008, 63, 34, 63, 1005, 63, 733, 1105, 1, 739, 4, 719, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 3, 21101, 46, 0, 1, 1008, 1014, 46, 63, 1005, 63, 761, 4, 745, 1106, 0, 765, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 5, 21102, 47, 1, -7, 1008, 1011, 47, 63, 1005, 63, 787, 4, 771, 1105, 1, 791, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -24, 2108, 24, 8, 63, 1005, 63, 813, 4, 797, 1001, 64, 1, 64, 1106, 0, 813, 1002, 64, 2, 64, 109, 5, 1208, 10, 29, 63, 1005, 63, 829, 1105, 1, 835, 4, 819, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 7, 2107, 23, -4, 63, 1005, 63, 853, 4, 841, 1105, 1, 857, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, -2, 1202, 0, 1, 63, 1008, 63, 21, 63, 1005, 63, 879, 4, 863, 1105, 1, 883, 1001, 64, 1, 64, 1002, 64, 2, 64, 109, 15, 2106, 0, 8, 1106, 0, 901, 4, 889, 1001, 64, 1, 64, 4, 64, 99, 21102, 1, 27, 1, 21102, 915, 1, 0, 1105, 1, 922, 21201, 1, 51839, 1, 204, 1, 99, 109, 3, 1207, -2, 3, 63, 1005, 63, 964, 21201, -2, -1, 1, 21101, 942, 0, 0, 1106, 0, 922, 21201, 1, 0, -1, 21201, -2, -3, 1, 21101, 957, 0, 0, 1105, 1, 922, 22201, 1, -1, -2, 1105, 1, 968, 21201, -2, 0, -2, 109, -3, 2106, 0, 0];
+
+fn is_code(addr: i64) -> bool {
+    matches!(addr, 0..=62 | 65..=241 | 904..=972)
+}
+
+// The default memory limit of `intcode::Computer`
+const MEMORY_LIMIT: usize = 16777216;
+
+struct Memory {
+    words: Vec<i64>,
+    // addresses of translated code the program has written to
+    changed: BTreeSet<usize>,
+}
+
+impl Memory {
+    fn new() -> Memory {
+        Memory {
+            words: PROGRAM.to_vec(),
+            changed: BTreeSet::new(),
+        }
+    }
+
+    fn get(&self, addr: i64) -> Result<i64> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            bail!("Invalid read from {}", addr);
+        }
+        Ok(self.words.get(addr as usize).copied().unwrap_or(0))
+    }
+
+    fn set(&mut self, addr: i64, value: i64) -> Result<()> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            bail!("Invalid write to {}", addr);
+        }
+        let addr = addr as usize;
+        if is_code(addr as i64) {
+            self.changed.insert(addr);
+        }
+        if addr >= self.words.len() {
+            self.words.resize(addr + 1, 0);
+        }
+        self.words[addr] = value;
+        Ok(())
+    }
+
+    /// Whether the program has written to the translated code in `start..end`, which then
+    /// has to be interpreted instead.
+    fn changed(&self, start: usize, end: usize) -> bool {
+        !self.changed.is_empty() && self.changed.range(start..end).next().is_some()
+    }
+}
+
+fn add(a: i64, b: i64, _pc: usize) -> Result<i64> {
+    Ok(a.wrapping_add(b))
+}
+
+fn mul(a: i64, b: i64, _pc: usize) -> Result<i64> {
+    Ok(a.wrapping_mul(b))
+}
+
+/// Run the instruction at `pc`, for code that was not translated. Returns the next pc, or
+/// `None` if the program halted.
+async fn interpret<I: IO>(
+    mem: &mut Memory,
+    base: &mut i64,
+    pc: usize,
+    io: &mut I,
+) -> Result<Option<usize>> {
+    let opcode = mem.get(pc as i64)?;
+    let mode = |n: usize| opcode / 10_i64.pow(n as u32 + 1) % 10;
+    let read = |n: usize| -> Result<i64> {
+        let value = mem.get((pc + n) as i64)?;
+        match mode(n) {
+            0 => mem.get(value),
+            1 => Ok(value),
+            2 => mem.get(*base + value),
+            mode => bail!("Unknown mode {} at {}", mode, pc),
+        }
+    };
+    let addr = |n: usize| -> Result<i64> {
+        let value = mem.get((pc + n) as i64)?;
+        match mode(n) {
+            0 => Ok(value),
+            2 => Ok(*base + value),
+            mode => bail!("Invalid mode {} for a write at {}", mode, pc),
+        }
+    };
+    Ok(Some(match opcode % 100 {
+        1 | 2 | 7 | 8 => {
+            let (a, b) = (read(1)?, read(2)?);
+            let value = match opcode % 100 {
+                1 => add(a, b, pc)?,
+                2 => mul(a, b, pc)?,
+                7 => (a < b) as i64,
+                _ => (a == b) as i64,
+            };
+            let out = addr(3)?;
+            mem.set(out, value)?;
+            pc + 4
+        }
+        3 => {
+            let out = addr(1)?;
+            let value = io.input().await?;
+            mem.set(out, value)?;
+            pc + 2
+        }
+        4 => {
+            io.output(read(1)?).await?;
+            pc + 2
+        }
+        5 if read(1)? > 0 => read(2)? as usize,
+        6 if read(1)? == 0 => read(2)? as usize,
+        5 | 6 => pc + 3,
+        9 => {
+            let value = read(1)?;
+            *base += value;
+            pc + 2
+        }
+        99 => return Ok(None),
+        _ => bail!("Unknown opcode {} at {}", opcode, pc),
+    }))
+}
+
+pub async fn run<I: IO>(io: &mut I) -> Result<()> {
+    let mut mem = Memory::new();
+    let mut base: i64 = 0;
+    let mut pc: usize = 0;
+    loop {
+        match pc {
+            0 if !mem.changed(0, 11) => {
+                // 0: MUL 34463338 34463338 => &&63
+                mem.set(63, mul(34463338, 34463338, 0)?)?;
+                // 4: IF &63 < 34463338 => &63
+                mem.set(63, (mem.get(63)? < 34463338) as i64)?;
+                // 8: IF &63 JUMP TO 53
+                if mem.get(63)? > 0 {
+                    pc = 53;
+                    continue;
+                }
+                pc = 11;
+            }
+            11 if !mem.changed(11, 21) => {
+                // 11: ADD 3 0 => &&1000
+                mem.set(1000, add(3, 0, 11)?)?;
+                // 15: REL_BASE += 988
+                base += 988;
+                // 17: REL_BASE += base + 12
+                base += mem.get(base + 12)?;
+                // 19: REL_BASE += &1000
+                base += mem.get(1000)?;
+                pc = 21;
+            }
+            21 if !mem.changed(21, 23) => {
+                // 21: REL_BASE += base + 6
+                base += mem.get(base + 6)?;
+                pc = 23;
+            }
+            23 if !mem.changed(23, 25) => {
+                // 23: REL_BASE += base + 3
+                base += mem.get(base + 3)?;
+                pc = 25;
+            }
+            25 if !mem.changed(25, 27) => {
+                // 25: INPUT &base + 0
+                mem.set(base, io.input().await?)?;
+                pc = 27;
+            }
+            27 if !mem.changed(27, 31) => {
+                // 27: IF &1000 == 1 => &63
+                mem.set(63, (mem.get(1000)? == 1) as i64)?;
+                pc = 31;
+            }
+            31 if !mem.changed(31, 34) => {
+                // 31: IF &63 JUMP TO 65
+                if mem.get(63)? > 0 {
+                    pc = 65;
+                    continue;
+                }
+                pc = 34;
+            }
+            34 if !mem.changed(34, 38) => {
+                // 34: IF &1000 == 2 => &63
+                mem.set(63, (mem.get(1000)? == 2) as i64)?;
+                pc = 38;
+            }
+            38 if !mem.changed(38, 41) => {
+                // 38: IF &63 JUMP TO 904
+                if mem.get(63)? > 0 {
+                    pc = 904;
+                    continue;
+                }
+                pc = 41;
+            }
+            41 if !mem.changed(41, 48) => {
+                // 41: IF &1000 == 0 => &63
+                mem.set(63, (mem.get(1000)? == 0) as i64)?;
+                // 45: IF &63 JUMP TO 58
+                if mem.get(63)? > 0 {
+                    pc = 58;
+                    continue;
+                }
+                pc = 48;
+            }
+            48 if !mem.changed(48, 53) => {
+                // 48: OUTPUT &25
+                io.output(mem.get(25)?).await?;
+                // 50: OUTPUT 0
+                io.output(0).await?;
+                // 52: HALT
+                return Ok(());
+            }
+            53 if !mem.changed(53, 58) => {
+                // 53: OUTPUT &0
+                io.output(mem.get(0)?).await?;
+                // 55: OUTPUT 0
+                io.output(0).await?;
+                // 57: HALT
+                return Ok(());
+            }
+            58 if !mem.changed(58, 63) => {
+                // 58: OUTPUT &17
+                io.output(mem.get(17)?).await?;
+                // 60: OUTPUT 0
+                io.output(0).await?;
+                // 62: HALT
+                return Ok(());
+            }
+            65 if !mem.changed(65, 198) => {
+                // 65: ADD 35 0 => &&1007
+                mem.set(1007, add(35, 0, 65)?)?;
+                // 69: MUL 30 1 => &&1013
+                mem.set(1013, mul(30, 1, 69)?)?;
+                // 73: MUL 37 1 => &&1017
+                mem.set(1017, mul(37, 1, 73)?)?;
+                // 77: ADD 23 0 => &&1006
+                mem.set(1006, add(23, 0, 77)?)?;
+                // 81: ADD 0 32 => &&1008
+                mem.set(1008, add(0, 32, 81)?)?;
+                // 85: MUL 1 29 => &&1000
+                mem.set(1000, mul(1, 29, 85)?)?;
+                // 89: ADD 0 38 => &&1010
+                mem.set(1010, add(0, 38, 89)?)?;
+                // 93: ADD 0 24 => &&1002
+                mem.set(1002, add(0, 24, 93)?)?;
+                // 97: ADD 33 0 => &&1003
+                mem.set(1003, add(33, 0, 97)?)?;
+                // 101: ADD 1 0 => &&1021
+                mem.set(1021, add(1, 0, 101)?)?;
+                // 105: MUL 31 1 => &&1019
+                mem.set(1019, mul(31, 1, 105)?)?;
+                // 109: ADD 27 0 => &&1014
+                mem.set(1014, add(27, 0, 109)?)?;
+                // 113: MUL 20 1 => &&1005
+                mem.set(1005, mul(20, 1, 113)?)?;
+                // 117: ADD 0 0 => &&1020
+                mem.set(1020, add(0, 0, 117)?)?;
+                // 121: MUL 1 892 => &&1027
+                mem.set(1027, mul(1, 892, 121)?)?;
+                // 125: ADD 895 0 => &&1026
+                mem.set(1026, add(895, 0, 125)?)?;
+                // 129: MUL 39 1 => &&1015
+                mem.set(1015, mul(39, 1, 129)?)?;
+                // 133: MUL 1 370 => &&1029
+                mem.set(1029, mul(1, 370, 133)?)?;
+                // 137: MUL 1 28 => &&1001
+                mem.set(1001, mul(1, 28, 137)?)?;
+                // 141: MUL 34 1 => &&1012
+                mem.set(1012, mul(34, 1, 141)?)?;
+                // 145: ADD 25 0 => &&1016
+                mem.set(1016, add(25, 0, 145)?)?;
+                // 149: ADD 0 375 => &&1028
+                mem.set(1028, add(0, 375, 149)?)?;
+                // 153: ADD 36 0 => &&1018
+                mem.set(1018, add(36, 0, 153)?)?;
+                // 157: ADD 0 21 => &&1004
+                mem.set(1004, add(0, 21, 157)?)?;
+                // 161: MUL 1 26 => &&1009
+                mem.set(1009, mul(1, 26, 161)?)?;
+                // 165: ADD 0 249 => &&1022
+                mem.set(1022, add(0, 249, 165)?)?;
+                // 169: ADD 0 660 => &&1025
+                mem.set(1025, add(0, 660, 169)?)?;
+                // 173: ADD 0 665 => &&1024
+                mem.set(1024, add(0, 665, 173)?)?;
+                // 177: MUL 1 22 => &&1011
+                mem.set(1011, mul(1, 22, 177)?)?;
+                // 181: MUL 242 1 => &&1023
+                mem.set(1023, mul(242, 1, 181)?)?;
+                // 185: REL_BASE += 5
+                base += 5;
+                // 187: MUL 1 base + 3 => &&63
+                mem.set(63, mul(1, mem.get(base + 3)?, 187)?)?;
+                // 191: IF &63 == 31 => &63
+                mem.set(63, (mem.get(63)? == 31) as i64)?;
+                // 195: IF &63 JUMP TO 205
+                if mem.get(63)? > 0 {
+                    pc = 205;
+                    continue;
+                }
+                pc = 198;
+            }
+            198 if !mem.changed(198, 205) => {
+                // 198: ADD &64 1 => &&64
+                mem.set(64, add(mem.get(64)?, 1, 198)?)?;
+                // 202: IF 1 JUMP TO 207
+                pc = 207;
+                continue;
+            }
+            205 if !mem.changed(205, 207) => {
+                // 205: OUTPUT &187
+                io.output(mem.get(187)?).await?;
+                pc = 207;
+            }
+            207 if !mem.changed(207, 224) => {
+                // 207: MUL &64 2 => &&64
+                mem.set(64, mul(mem.get(64)?, 2, 207)?)?;
+                // 211: REL_BASE += 8
+                base += 8;
+                // 213: MUL 40 1 => &base + 5
+                mem.set(base + 5, mul(40, 1, 213)?)?;
+                if mem.changed(207, 224) { pc = 217; continue; }
+                // 217: IF &1018 == 37 => &63
+                mem.set(63, (mem.get(1018)? == 37) as i64)?;
+                // 221: IF &63 JUMP TO 227
+                if mem.get(63)? > 0 {
+                    pc = 227;
+                    continue;
+                }
+                pc = 224;
+            }
+            224 if !mem.changed(224, 227) => {
+                // 224: IF 1 JUMP TO 233
+                pc = 233;
+                continue;
+            }
+            227 if !mem.changed(227, 233) => {
+                // 227: OUTPUT &213
+                io.output(mem.get(213)?).await?;
+                // 229: ADD &64 1 => &&64
+                mem.set(64, add(mem.get(64)?, 1, 229)?)?;
+                pc = 233;
+            }
+            233 if !mem.changed(233, 242) => {
+                // 233: MUL &64 2 => &&64
+                mem.set(64, mul(mem.get(64)?, 2, 233)?)?;
+                // 237: REL_BASE += 7
+                base += 7;
+                // 239: IF 1 JUMP TO base + 3
+                pc = mem.get(base + 3)? as usize;
+                continue;
+            }
+            904 if !mem.changed(904, 915) => {
+                // 904: MUL 1 27 => &base + 1
+                mem.set(base + 1, mul(1, 27, 904)?)?;
+                if mem.changed(904, 915) { pc = 908; continue; }
+                // 908: MUL 915 1 => &base + 0
+                mem.set(base, mul(915, 1, 908)?)?;
+                if mem.changed(904, 915) { pc = 912; continue; }
+                // 912: IF 1 JUMP TO 922
+                pc = 922;
+                continue;
+            }
+            915 if !mem.changed(915, 922) => {
+                // 915: ADD base + 1 51839 => &base + 1
+                mem.set(base + 1, add(mem.get(base + 1)?, 51839, 915)?)?;
+                if mem.changed(915, 922) { pc = 919; continue; }
+                // 919: OUTPUT base + 1
+                io.output(mem.get(base + 1)?).await?;
+                // 921: HALT
+                return Ok(());
+            }
+            922 if !mem.changed(922, 931) => {
+                // 922: REL_BASE += 3
+                base += 3;
+                // 924: IF base + -2 < 3 => &63
+                mem.set(63, (mem.get(base + -2)? < 3) as i64)?;
+                // 928: IF &63 JUMP TO 964
+                if mem.get(63)? > 0 {
+                    pc = 964;
+                    continue;
+                }
+                pc = 931;
+            }
+            931 if !mem.changed(931, 942) => {
+                // 931: ADD base + -2 -1 => &base + 1
+                mem.set(base + 1, add(mem.get(base + -2)?, (-1), 931)?)?;
+                if mem.changed(931, 942) { pc = 935; continue; }
+                // 935: ADD 942 0 => &base + 0
+                mem.set(base, add(942, 0, 935)?)?;
+                if mem.changed(931, 942) { pc = 939; continue; }
+                // 939: IF NOT 0 JUMP TO 922
+                pc = 922;
+                continue;
+            }
+            942 if !mem.changed(942, 957) => {
+                // 942: ADD base + 1 0 => &base + -1
+                mem.set(base + -1, add(mem.get(base + 1)?, 0, 942)?)?;
+                if mem.changed(942, 957) { pc = 946; continue; }
+                // 946: ADD base + -2 -3 => &base + 1
+                mem.set(base + 1, add(mem.get(base + -2)?, (-3), 946)?)?;
+                if mem.changed(942, 957) { pc = 950; continue; }
+                // 950: ADD 957 0 => &base + 0
+                mem.set(base, add(957, 0, 950)?)?;
+                if mem.changed(942, 957) { pc = 954; continue; }
+                // 954: IF 1 JUMP TO 922
+                pc = 922;
+                continue;
+            }
+            957 if !mem.changed(957, 964) => {
+                // 957: ADD base + 1 base + -1 => &base + -2
+                mem.set(base + -2, add(mem.get(base + 1)?, mem.get(base + -1)?, 957)?)?;
+                if mem.changed(957, 964) { pc = 961; continue; }
+                // 961: IF 1 JUMP TO 968
+                pc = 968;
+                continue;
+            }
+            964 if !mem.changed(964, 968) => {
+                // 964: ADD base + -2 0 => &base + -2
+                mem.set(base + -2, add(mem.get(base + -2)?, 0, 964)?)?;
+                pc = 968;
+            }
+            968 if !mem.changed(968, 973) => {
+                // 968: REL_BASE += -3
+                base += (-3);
+                // 970: IF NOT 0 JUMP TO base + 0
+                pc = mem.get(base)? as usize;
+                continue;
+            }
+            _ => match interpret(&mut mem, &mut base, pc, io).await? {
+                Some(next) => pc = next,
+                None => return Ok(()),
+            },
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Runs programs translated by `intcode-to-rust`. The translations are checked in, so a
+//! change to the generated code shows up in review. After changing the transpiler, update
+//! them with
+//!
+//! ```text
+//! cargo run --bin intcode-to-rust ../aoc05/input/input.txt > tests/transpile/aoc05.rs
+//! cargo run --bin intcode-to-rust ../aoc09/input/input.txt > tests/transpile/aoc09.rs
+//! ```
+
+mod aoc05;
+mod aoc09;
+
+use anyhow::Result;
+
+use intcode::transpile::transpile;
+use intcode::{Computer, QueueIO};
+
+const AOC05: &str = include_str!("../../../aoc05/input/input.txt");
+const AOC09: &str = include_str!("../../../aoc09/input/input.txt");
+
+#[test]
+fn translations_are_current() -> Result<()> {
+    let program = intcode::parse_program(AOC05)?;
+    assert!(
+        transpile(&program)? == include_str!("aoc05.rs"),
+        "tests/transpile/aoc05.rs is out of date"
+    );
+    let program = intcode::parse_program(AOC09)?;
+    assert!(
+        transpile(&program)? == include_str!("aoc09.rs"),
+        "tests/transpile/aoc09.rs is out of date"
+    );
+    Ok(())
+}
+
+// Day 5 patches code the disassembler can't reach, and in part 2 writes over translated
+// code at 0
+#[async_std::test]
+async fn aoc05() -> Result<()> {
+    let program = intcode::parse_program(AOC05)?;
+    for &input in &[1, 5] {
+        let mut io = QueueIO::new(&[input]);
+        aoc05::run(&mut io).await?;
+        let expected = Computer::from_mem(program.clone()).run_with_inputs(&[input])?;
+        assert_eq!(io.output, expected);
+    }
+    Ok(())
+}
+
+// Day 9 is almost all translated: BOOST calls functions through the relative base, and
+// part 2 runs a few hundred thousand instructions
+#[async_std::test]
+async fn aoc09() -> Result<()> {
+    let program = intcode::parse_program(AOC09)?;
+    for &input in &[1, 2] {
+        let mut io = QueueIO::new(&[input]);
+        aoc09::run(&mut io).await?;
+        let expected = Computer::from_mem(program.clone()).run_with_inputs(&[input])?;
+        assert_eq!(io.output, expected);
+    }
+    Ok(())
+}